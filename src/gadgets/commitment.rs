@@ -0,0 +1,121 @@
+use ark_relations::r1cs::{ConstraintSystem, SynthesisError, OptimizationGoal};
+use ark_crypto_primitives::commitment::{
+    pedersen::{self, constraints::CommGadget, Randomness},
+    CommitmentGadget,
+    CommitmentScheme,
+};
+use ark_ed_on_bls12_377::{
+    Fq as ConstraintF,
+    Fr as ScalarF,
+    EdwardsProjective as JubJub,
+    constraints::EdwardsVar,
+};
+use ark_r1cs_std::{
+    alloc::AllocVar,
+    eq::EqGadget,
+    uint8::UInt8,
+    R1CSVar,
+};
+use ark_std::UniformRand;
+
+use crate::utils::print_cs_details;
+
+// Messages of up to 4 * 128 bits = 64 bytes
+#[derive(Clone)]
+pub struct CommitmentWindow;
+impl pedersen::Window for CommitmentWindow {
+    const WINDOW_SIZE: usize = 128;
+    const NUM_WINDOWS: usize = 4;
+}
+
+// Pedersen commitment on ed_on_bls12_377: sum of message-bit generators plus r * h,
+// where h has its own generator powers. Its gadget works over BLS12-377 Fr.
+pub type PedersenCommitment = pedersen::Commitment<JubJub, CommitmentWindow>;
+pub type PedersenCommitmentGadget = CommGadget<JubJub, EdwardsVar, CommitmentWindow>;
+
+pub const COMMITMENT_MESSAGE_LEN: usize = 64;
+
+pub fn test_pedersen_commitment() -> Result<(), SynthesisError> {
+    println!("\n### Running test_pedersen_commitment()...");
+
+    let mut rng = ark_std::test_rng();
+    let params = PedersenCommitment::setup(&mut rng).unwrap();
+
+    // Native and in-circuit commitments agree
+    for len in [0, 1, 5, 32, 63, COMMITMENT_MESSAGE_LEN] {
+        let message: Vec<u8> = (0..len).map(|_| u8::rand(&mut rng)).collect();
+        let randomness = Randomness::<JubJub>(ScalarF::rand(&mut rng));
+        let commitment = PedersenCommitment::commit(&params, &message, &randomness).unwrap();
+
+        let cs = ConstraintSystem::<ConstraintF>::new_ref();
+        let params_var = pedersen::constraints::ParametersVar::new_constant(
+            ark_relations::ns!(cs, "Commitment Parameters"),
+            &params,
+        )?;
+        let message_var = UInt8::new_witness_vec(ark_relations::ns!(cs, "message"), &message)?;
+        let randomness_var = pedersen::constraints::RandomnessVar::new_witness(
+            ark_relations::ns!(cs, "randomness"),
+            || Ok(&randomness),
+        )?;
+        let commitment_var = EdwardsVar::new_input(ark_relations::ns!(cs, "commitment"), || Ok(commitment))?;
+
+        let result_var = PedersenCommitmentGadget::commit(&params_var, &message_var, &randomness_var)?;
+        assert_eq!(result_var.value()?, commitment);
+        result_var.enforce_equal(&commitment_var)?;
+        assert!(cs.is_satisfied()?);
+    }
+
+    // Hiding: the same message under fresh randomness gives unrelated commitments
+    let message = b"hello".to_vec();
+    let r_1 = Randomness::<JubJub>(ScalarF::rand(&mut rng));
+    let r_2 = Randomness::<JubJub>(ScalarF::rand(&mut rng));
+    let commitment = PedersenCommitment::commit(&params, &message, &r_1).unwrap();
+    assert_ne!(commitment, PedersenCommitment::commit(&params, &message, &r_2).unwrap());
+
+    // Binding: no other opening satisfies the circuit for this commitment
+    let wrong_openings = [
+        (b"hellp".to_vec(), r_1.clone()),
+        (message.clone(), r_2.clone()),
+    ];
+    for (wrong_message, wrong_randomness) in wrong_openings.iter() {
+        let cs = ConstraintSystem::<ConstraintF>::new_ref();
+        let params_var = pedersen::constraints::ParametersVar::new_constant(
+            ark_relations::ns!(cs, "Commitment Parameters"),
+            &params,
+        )?;
+        let message_var = UInt8::new_witness_vec(ark_relations::ns!(cs, "message"), wrong_message)?;
+        let randomness_var = pedersen::constraints::RandomnessVar::new_witness(
+            ark_relations::ns!(cs, "randomness"),
+            || Ok(wrong_randomness),
+        )?;
+        let commitment_var = EdwardsVar::new_input(ark_relations::ns!(cs, "commitment"), || Ok(commitment))?;
+        PedersenCommitmentGadget::commit(&params_var, &message_var, &randomness_var)?.enforce_equal(&commitment_var)?;
+        assert!(!cs.is_satisfied()?);
+    }
+
+    // NOTE: messages are zero-padded to the windows, so trailing zero bytes give the same
+    // commitment. Circuits must fix the message length.
+    assert_eq!(PedersenCommitment::commit(&params, b"hello\0", &r_1).unwrap(), commitment);
+
+    // Cost: message windows vs randomness
+    let cs = ConstraintSystem::<ConstraintF>::new_ref();
+    cs.set_optimization_goal(OptimizationGoal::None);
+    let params_var = pedersen::constraints::ParametersVar::new_constant(
+        ark_relations::ns!(cs, "Commitment Parameters"),
+        &params,
+    )?;
+    let message_var = UInt8::new_witness_vec(ark_relations::ns!(cs, "message"), &message)?;
+    let randomness_var = pedersen::constraints::RandomnessVar::new_witness(
+        ark_relations::ns!(cs, "randomness"),
+        || Ok(&r_1),
+    )?;
+    let commitment_var = EdwardsVar::new_input(ark_relations::ns!(cs, "commitment"), || Ok(commitment))?;
+    PedersenCommitmentGadget::commit(&params_var, &message_var, &randomness_var)?.enforce_equal(&commitment_var)?;
+    assert!(cs.is_satisfied()?);
+
+    cs.finalize();
+
+    print_cs_details(cs);
+
+    Ok(())
+}
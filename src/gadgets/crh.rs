@@ -6,13 +6,34 @@ use ark_crypto_primitives::crh::{
 use ark_r1cs_std::{
     alloc::{AllocVar, AllocationMode}, eq::EqGadget, groups::CurveVar, uint8::UInt8, R1CSVar, ToBytesGadget
 };
+use ark_crypto_primitives::{
+    crh::{bowe_hopwood, pedersen::Window, poseidon, sha256::constraints::UnitVar},
+    prf::{
+        blake2s::constraints::{evaluate_blake2s_with_parameters, Blake2sGadget},
+        Blake2s,
+        PRFGadget,
+        PRF,
+    },
+    sponge::poseidon::PoseidonConfig,
+    CryptoError,
+    Error,
+};
+use ark_ff::{BigInteger, Field, PrimeField, ToConstraintField};
 use ark_ed_on_bls12_377::{
     Fq as ConstraintF,
+    EdwardsAffine,
+    EdwardsConfig,
     EdwardsProjective as JubJub,
     constraints::EdwardsVar,
 };
+use ark_r1cs_std::{fields::{fp::FpVar, FieldVar}, ToBitsGadget, ToConstraintFieldGadget};
+use ark_std::{rand::Rng, UniformRand};
+use blake2::{digest::Mac, Blake2s256, Blake2sMac256, Digest};
+use std::time::{Duration, Instant};
 
-use crate::utils::print_cs_details;
+use crate::utils::{hex_to_bytes, print_cs_details};
+use crate::utils::mimc::{mimc_feistel, mimc_feistel_hash, mimc_hash, MimcParameters, MIMC_SEED};
+use crate::utils::poseidon::poseidon_parameters;
 
 pub fn test_sha256_crh() -> Result<(), SynthesisError> {
     println!("\n### Running test_sha256_crh()...");
@@ -45,53 +66,936 @@ pub fn test_sha256_crh() -> Result<(), SynthesisError> {
     Ok(())
 }
 
+// BLAKE2s-256 on the same UInt8 plumbing as Sha256Gadget
+pub fn blake2s_gadget<F: PrimeField>(input: &[UInt8<F>]) -> Result<Vec<UInt8<F>>, SynthesisError> {
+    blake2s_keyed_gadget(&[], input)
+}
+
+// Keyed BLAKE2s-256 (RFC 7693): the key length goes into the parameter block and the key,
+// zero-padded to a full block, is compressed before the message
+pub fn blake2s_keyed_gadget<F: PrimeField>(
+    key: &[UInt8<F>],
+    input: &[UInt8<F>],
+) -> Result<Vec<UInt8<F>>, SynthesisError> {
+    assert!(key.len() <= 32);
+
+    let mut parameters = [0; 8];
+    parameters[0] = 0x01010000 ^ ((key.len() as u32) << 8) ^ 32;
+
+    let mut bits = Vec::new();
+    if !key.is_empty() {
+        let padding = vec![UInt8::constant(0); 64 - key.len()];
+        for byte in key.iter().chain(padding.iter()) {
+            bits.extend(byte.to_bits_le()?);
+        }
+    }
+    for byte in input {
+        bits.extend(byte.to_bits_le()?);
+    }
+
+    let mut digest = Vec::new();
+    for word in evaluate_blake2s_with_parameters(&bits, &parameters)? {
+        digest.extend(word.to_bytes()?);
+    }
+    Ok(digest)
+}
+
+// PRF of prf::blake2s: BLAKE2s-256(seed || input) with 32-byte seed and input
+pub fn blake2s_prf_gadget<F: PrimeField>(
+    seed: &[UInt8<F>],
+    input: &[UInt8<F>],
+) -> Result<Vec<UInt8<F>>, SynthesisError> {
+    assert_eq!(input.len(), 32);
+    <Blake2sGadget as PRFGadget<Blake2s, F>>::evaluate(seed, input)?.to_bytes()
+}
+
+fn blake2s_keyed(key: &[u8], input: &[u8]) -> Vec<u8> {
+    if key.is_empty() {
+        return Blake2s256::digest(input).to_vec();
+    }
+    let mut mac = <Blake2sMac256 as Mac>::new_from_slice(key).unwrap();
+    Mac::update(&mut mac, input);
+    mac.finalize().into_bytes().to_vec()
+}
+
+pub fn test_blake2s_crh() -> Result<(), SynthesisError> {
+    println!("\n### Running test_blake2s_crh()...");
+
+    // RFC 7693 Appendix B, and the keyed vectors of the BLAKE2 reference KAT (key 00..1f)
+    let key: Vec<u8> = (0..32).collect();
+    let vectors: [(&[u8], &[u8], &str); 5] = [
+        (&[], b"", "69217a3079908094e11121d042354a7c1f55b6482ca1a51e1b250dfd1ed0eef9"),
+        (&[], b"abc", "508c5e8c327c14e2e1a72ba34eeb452f37458b209ed63a294d999b4c86675982"),
+        (&[], b"The quick brown fox jumps over the lazy dog", "606beeec743ccbeff6cbcdf5d5302aa855c256c29b88c8ed331ea1a6bf3c8812"),
+        (&key, b"", "48a8997da407876b3d79c0d92325ad3b89cbb754d86ab71aee047ad345fd2c49"),
+        (&key, &[0x00], "40d15fee7c328830166ac3f918650f807e7e01e177258cdc0a39b11f598066f1"),
+    ];
+
+    for (key, msg, expected) in vectors {
+        let expected = hex_to_bytes(expected);
+        assert_eq!(blake2s_keyed(key, msg), expected);
+
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let key_var = UInt8::new_witness_vec(ark_relations::ns!(cs, "key"), key)?;
+        let msg_var = UInt8::new_witness_vec(ark_relations::ns!(cs, "msg"), msg)?;
+        let output_var = UInt8::new_input_vec(ark_relations::ns!(cs, "output"), &expected)?;
+
+        let digest_var = blake2s_keyed_gadget(&key_var, &msg_var)?;
+        assert_eq!(digest_var.value()?, expected);
+        digest_var.enforce_equal(&output_var)?;
+        assert!(cs.is_satisfied()?);
+    }
+    println!("{} test vectors checked", vectors.len());
+
+    // Native and in-circuit hashes agree across block boundaries
+    let mut rng = ark_std::test_rng();
+    for len in [1, 63, 64, 65, 127, 128, 129, 200] {
+        let msg: Vec<u8> = (0..len).map(|_| u8::rand(&mut rng)).collect();
+
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let msg_var = UInt8::new_witness_vec(ark_relations::ns!(cs, "msg"), &msg)?;
+        assert_eq!(blake2s_gadget(&msg_var)?.value()?, blake2s_keyed(&[], &msg));
+    }
+
+    // PRF
+    let seed = [7u8; 32];
+    let input = [9u8; 32];
+    let prf_output = Blake2s::evaluate(&seed, &input).unwrap();
+
+    let cs = ConstraintSystem::<Fr>::new_ref();
+    let seed_var = <Blake2sGadget as PRFGadget<Blake2s, Fr>>::new_seed(ark_relations::ns!(cs, "seed"), &seed);
+    let input_var = UInt8::new_witness_vec(ark_relations::ns!(cs, "input"), &input)?;
+    let output_var = UInt8::new_input_vec(ark_relations::ns!(cs, "output"), &prf_output)?;
+    blake2s_prf_gadget(&seed_var, &input_var)?.enforce_equal(&output_var)?;
+    assert!(cs.is_satisfied()?);
+    println!("PRF constraints: {}", cs.num_constraints());
+
+    // Another seed gives another output
+    let cs = ConstraintSystem::<Fr>::new_ref();
+    let seed_var = <Blake2sGadget as PRFGadget<Blake2s, Fr>>::new_seed(ark_relations::ns!(cs, "seed"), &[8u8; 32]);
+    let input_var = UInt8::new_witness_vec(ark_relations::ns!(cs, "input"), &input)?;
+    let output_var = UInt8::new_input_vec(ark_relations::ns!(cs, "output"), &prf_output)?;
+    blake2s_prf_gadget(&seed_var, &input_var)?.enforce_equal(&output_var)?;
+    assert!(!cs.is_satisfied()?);
+
+    // Same input as test_sha256_crh
+    let cs = ConstraintSystem::<Fr>::new_ref();
+    cs.set_optimization_goal(OptimizationGoal::None);
+
+    let input = String::from("hello");
+    let output = blake2s_keyed(&[], input.as_bytes());
+
+    let input_var = UInt8::new_witness_vec(ark_relations::ns!(cs, "input"), input.as_bytes())?;
+    let output_var = UInt8::new_input_vec(ark_relations::ns!(cs, "output"), &output)?;
+    blake2s_gadget(&input_var)?.enforce_equal(&output_var)?;
+
+    assert!(cs.is_satisfied()?);
+
+    cs.finalize();
+
+    print_cs_details(cs);
+
+    Ok(())
+}
+
+// FIPS 180-4 examples and messages around the padding boundaries: 55 bytes is the longest
+// message whose length fits in the same block, 56 and 64 bytes need a second block
+pub fn test_sha256_vectors() -> Result<(), SynthesisError> {
+    println!("\n### Running test_sha256_vectors()...");
+
+    let vectors: Vec<(Vec<u8>, &str)> = vec![
+        (b"".to_vec(), "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"),
+        (b"abc".to_vec(), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"),
+        (
+            b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq".to_vec(),
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1",
+        ),
+        (
+            b"abcdefghbcdefghicdefghijdefghijkefghijklfghijklmghijklmnhijklmnoijklmnopjklmnopqklmnopqrlmnopqrsmnopqrstnopqrstu".to_vec(),
+            "cf5b16a778af8380036ce59e7b0492370b249b11e8f07a51afac45037afee9d1",
+        ),
+        (vec![b'a'; 55], "9f4390f8d30c2dd92ec9f095b65e2b9ae9b0a925a5258e241c9f1e910f734318"),
+        (vec![b'a'; 56], "b35439a4ac6f0948b6d6f9e3c6af0f5f590ce20f1bde7090ef7970686ec6738a"),
+        (vec![b'a'; 63], "7d3e74a05d7db15bce4ad9ec0658ea98e3f06eeecf16b4c6fff2da457ddc2f34"),
+        (vec![b'a'; 64], "ffe054fe7ae0cb6dc65c3af9b61d5209f439851db43d0ba5997337df154668eb"),
+        (vec![b'a'; 65], "635361c48bb9eab14198e76ea8ab7f1a41685d6ad62aa9146d301d4f17eb0ae0"),
+        (vec![b'a'; 119], "31eba51c313a5c08226adf18d4a359cfdfd8d2e816b13f4af952f7ea6584dcfb"),
+        (vec![b'a'; 120], "2f3d335432c70b580af0e8e1b3674a7c020d683aa5f73aaaedfdc55af904c21c"),
+        (vec![b'a'; 128], "6836cf13bac400e9105071cd6af47084dfacad4e5e302c94bfed24e013afb73e"),
+    ];
+
+    // One million 'a': too large for a circuit, checked natively only
+    assert_eq!(
+        Sha256::evaluate(&(), vec![b'a'; 1_000_000]).unwrap(),
+        hex_to_bytes("cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0"),
+    );
+
+    println!("{:>8} | {:>8} | {:>12}", "bytes", "blocks", "constraints");
+    for (msg, expected) in vectors.iter() {
+        let expected = hex_to_bytes(expected);
+        assert_eq!(Sha256::evaluate(&(), msg.as_slice()).unwrap(), expected);
+
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let msg_var = UInt8::new_witness_vec(ark_relations::ns!(cs, "msg"), msg)?;
+        let output_var = UInt8::new_input_vec(ark_relations::ns!(cs, "output"), &expected)?;
+
+        let digest_var = Sha256Gadget::evaluate(&UnitVar::default(), &msg_var)?.to_bytes()?;
+        assert_eq!(digest_var.value()?, expected);
+        digest_var.enforce_equal(&output_var)?;
+        assert!(cs.is_satisfied()?);
+        let constraints = cs.num_constraints();
+
+        // Absorbing the message in two updates gives the same digest
+        let mut sha_var = Sha256Gadget::<Fr>::default();
+        sha_var.update(&msg_var[..msg.len() / 2])?;
+        sha_var.update(&msg_var[msg.len() / 2..])?;
+        sha_var.finalize()?.to_bytes()?.enforce_equal(&output_var)?;
+        assert!(cs.is_satisfied()?);
+
+        // Length in bits takes 8 bytes after the 0x80 marker
+        let blocks = (msg.len() + 1 + 8).div_ceil(64);
+        println!("{:>8} | {:>8} | {:>12}", msg.len(), blocks, constraints);
+    }
+
+    // A single flipped digest bit does not satisfy the circuit
+    let (msg, expected) = &vectors[5];
+    let mut wrong = hex_to_bytes(expected);
+    wrong[31] ^= 1;
+
+    let cs = ConstraintSystem::<Fr>::new_ref();
+    let msg_var = UInt8::new_witness_vec(ark_relations::ns!(cs, "msg"), msg)?;
+    let output_var = UInt8::new_input_vec(ark_relations::ns!(cs, "output"), &wrong)?;
+    Sha256Gadget::evaluate(&UnitVar::default(), &msg_var)?.to_bytes()?.enforce_equal(&output_var)?;
+    assert!(!cs.is_satisfied()?);
+
+    Ok(())
+}
+
 // HASH TO CURVE
 // The crh works on twisted Edw with Fq = Bls12_Fr (for the constraint system)
 pub fn test_pedersen_crh() -> Result<(), SynthesisError> {
-    println!("\n### Running test_sha256_crh()...");
+    println!("\n### Running test_pedersen_crh()...");
 
-    let cs = ConstraintSystem::<ConstraintF>::new_ref();
-    cs.set_optimization_goal(OptimizationGoal::None);
+    let input = String::from("hello");
 
-    let mut rng = ark_std::test_rng();
+    let report = PedersenWindowConfig::default()
+        .run(input.as_bytes(), OptimizationGoal::None)
+        .unwrap();
 
-    let input = String::from("hello");
+    print_cs_details(report.cs);
+
+    Ok(())
+}
+
+// Pedersen windows selectable at runtime. pedersen::Window fixes the sizes at compile time,
+// so each configuration is its own type and PedersenWindowConfig dispatches to it.
+macro_rules! pedersen_window {
+    ($name:ident, $window_size:expr, $num_windows:expr) => {
+        #[derive(Clone)]
+        pub struct $name;
+        impl pedersen::Window for $name {
+            const WINDOW_SIZE: usize = $window_size;
+            const NUM_WINDOWS: usize = $num_windows;
+        }
+    };
+}
+
+pedersen_window!(Window32x16, 32, 16);
+pedersen_window!(Window64x8, 64, 8);
+pedersen_window!(Window128x4, 128, 4);
+pedersen_window!(Window256x2, 256, 2);
+pedersen_window!(Window128x8, 128, 8);
+pedersen_window!(Window256x4, 256, 4);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PedersenWindowConfig {
+    W32x16,
+    W64x8,
+    #[default]
+    W128x4,
+    W256x2,
+    W128x8,
+    W256x4,
+}
+
+impl PedersenWindowConfig {
+    pub const ALL: [PedersenWindowConfig; 6] = [
+        PedersenWindowConfig::W32x16,
+        PedersenWindowConfig::W64x8,
+        PedersenWindowConfig::W128x4,
+        PedersenWindowConfig::W256x2,
+        PedersenWindowConfig::W128x8,
+        PedersenWindowConfig::W256x4,
+    ];
+
+    pub fn window_size(&self) -> usize {
+        self.dims().0
+    }
+
+    pub fn num_windows(&self) -> usize {
+        self.dims().1
+    }
+
+    pub fn max_input_bits(&self) -> usize {
+        self.window_size() * self.num_windows()
+    }
 
-    #[derive(Clone)]
-    pub struct PedersenWindow;
-    impl pedersen::Window for PedersenWindow {
-        const WINDOW_SIZE: usize = 128;
-        const NUM_WINDOWS: usize = 4;
+    fn dims(&self) -> (usize, usize) {
+        match self {
+            PedersenWindowConfig::W32x16 => (Window32x16::WINDOW_SIZE, Window32x16::NUM_WINDOWS),
+            PedersenWindowConfig::W64x8 => (Window64x8::WINDOW_SIZE, Window64x8::NUM_WINDOWS),
+            PedersenWindowConfig::W128x4 => (Window128x4::WINDOW_SIZE, Window128x4::NUM_WINDOWS),
+            PedersenWindowConfig::W256x2 => (Window256x2::WINDOW_SIZE, Window256x2::NUM_WINDOWS),
+            PedersenWindowConfig::W128x8 => (Window128x8::WINDOW_SIZE, Window128x8::NUM_WINDOWS),
+            PedersenWindowConfig::W256x4 => (Window256x4::WINDOW_SIZE, Window256x4::NUM_WINDOWS),
+        }
     }
 
-    type PedersenCRH = pedersen::CRH<JubJub, PedersenWindow>;
-    type PedersenCRHGadget = pedersen::constraints::CRHGadget<JubJub, EdwardsVar, PedersenWindow>;
+    // Hashes `input` natively and in-circuit with this window configuration
+    pub fn run(&self, input: &[u8], goal: OptimizationGoal) -> Result<PedersenReport, Error> {
+        match self {
+            PedersenWindowConfig::W32x16 => run_pedersen::<Window32x16>(input, goal),
+            PedersenWindowConfig::W64x8 => run_pedersen::<Window64x8>(input, goal),
+            PedersenWindowConfig::W128x4 => run_pedersen::<Window128x4>(input, goal),
+            PedersenWindowConfig::W256x2 => run_pedersen::<Window256x2>(input, goal),
+            PedersenWindowConfig::W128x8 => run_pedersen::<Window128x8>(input, goal),
+            PedersenWindowConfig::W256x4 => run_pedersen::<Window256x4>(input, goal),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct PedersenInputTooLong {
+    pub input_bits: usize,
+    pub window_size: usize,
+    pub num_windows: usize,
+}
+
+impl std::fmt::Display for PedersenInputTooLong {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Pedersen input of {} bits exceeds WINDOW_SIZE * NUM_WINDOWS = {} * {} = {} bits",
+            self.input_bits,
+            self.window_size,
+            self.num_windows,
+            self.window_size * self.num_windows,
+        )
+    }
+}
+
+impl std::error::Error for PedersenInputTooLong {}
+
+fn check_pedersen_input_len<W: pedersen::Window>(input_len: usize) -> Result<(), Error> {
+    if input_len * 8 > W::WINDOW_SIZE * W::NUM_WINDOWS {
+        return Err(Box::new(PedersenInputTooLong {
+            input_bits: input_len * 8,
+            window_size: W::WINDOW_SIZE,
+            num_windows: W::NUM_WINDOWS,
+        }));
+    }
+    Ok(())
+}
+
+// pedersen::CRH::evaluate panics on long inputs, these check the length first
+pub fn pedersen_evaluate<W: pedersen::Window>(
+    params: &pedersen::Parameters<JubJub>,
+    input: &[u8],
+) -> Result<EdwardsAffine, Error> {
+    check_pedersen_input_len::<W>(input.len())?;
+    pedersen::CRH::<JubJub, W>::evaluate(params, input)
+}
+
+pub fn pedersen_evaluate_gadget<W: pedersen::Window>(
+    params_var: &pedersen::constraints::CRHParametersVar<JubJub, EdwardsVar>,
+    input_var: &[UInt8<ConstraintF>],
+) -> Result<EdwardsVar, Error> {
+    check_pedersen_input_len::<W>(input_var.len())?;
+    Ok(pedersen::constraints::CRHGadget::<JubJub, EdwardsVar, W>::evaluate(params_var, input_var)?)
+}
+
+pub struct PedersenReport {
+    pub cs: ConstraintSystemRef<ConstraintF>,
+    pub output: EdwardsAffine,
+    // Average time of one native evaluation
+    pub native_time: Duration,
+}
+
+const NATIVE_HASHES: u32 = 100;
 
-    let pedersen_params = PedersenCRH::setup(&mut rng).unwrap();
-    let hash_output = PedersenCRH::evaluate(&pedersen_params, input.as_bytes()).unwrap();
+fn run_pedersen<W: pedersen::Window>(input: &[u8], goal: OptimizationGoal) -> Result<PedersenReport, Error> {
+    let cs = ConstraintSystem::<ConstraintF>::new_ref();
+    cs.set_optimization_goal(goal);
+
+    let mut rng = ark_std::test_rng();
+
+    let pedersen_params = pedersen::CRH::<JubJub, W>::setup(&mut rng)?;
+    let hash_output = pedersen_evaluate::<W>(&pedersen_params, input)?;
+
+    let start = Instant::now();
+    for _ in 0..NATIVE_HASHES {
+        assert_eq!(pedersen_evaluate::<W>(&pedersen_params, input)?, hash_output);
+    }
+    let native_time = start.elapsed() / NATIVE_HASHES;
 
     // Constraint System
     // Can call new_constant from CRHParametersVar because it implements AllocVar trait
     let parameters_var = pedersen::constraints::CRHParametersVar::<JubJub, EdwardsVar>::new_constant(
         ark_relations::ns!(cs, "CRH Parameters"),
         &pedersen_params,
-    )
-    .unwrap();
-    let input_var = UInt8::new_witness_vec(ark_relations::ns!(cs, "input"), input.as_bytes())?;
+    )?;
+    let input_var = UInt8::new_witness_vec(ark_relations::ns!(cs, "input"), input)?;
     let output_var = EdwardsVar::new_variable(
         ark_relations::ns!(cs, "output"),
         || Ok(hash_output),
         AllocationMode::Input,
+    )?;
+    let result_var = pedersen_evaluate_gadget::<W>(&parameters_var, &input_var)?;
+
+    result_var.enforce_equal(&output_var)?;
+
+    assert!(cs.is_satisfied()?);
+
+    cs.finalize();
+
+    Ok(PedersenReport { cs, output: hash_output, native_time })
+}
+
+// Constraint count and native throughput of every window configuration
+pub fn test_pedersen_window_sweep() -> Result<(), SynthesisError> {
+    println!("\n### Running test_pedersen_window_sweep()...");
+
+    println!(
+        "{:>8} | {:>8} | {:>8} | {:>12} | {:>12} | {:>10}",
+        "window", "max bits", "bytes", "constraints", "hashes/s", "MiB/s",
     );
-    let result_var = PedersenCRHGadget::evaluate(&parameters_var, &input_var).unwrap();
-    
-    result_var.enforce_equal(&output_var.unwrap());
+    for config in PedersenWindowConfig::ALL {
+        for len in [16, 32, 64, 128] {
+            let input = vec![0xab; len];
+
+            let report = match config.run(&input, OptimizationGoal::Constraints) {
+                Ok(report) => report,
+                Err(e) => {
+                    assert!(len * 8 > config.max_input_bits());
+                    println!(
+                        "{:>8} | {:>8} | {:>8} | rejected: {}",
+                        format!("{}x{}", config.window_size(), config.num_windows()),
+                        config.max_input_bits(),
+                        len,
+                        e,
+                    );
+                    continue;
+                },
+            };
+
+            let elapsed = report.native_time.as_secs_f64();
+
+            println!(
+                "{:>8} | {:>8} | {:>8} | {:>12} | {:>12.0} | {:>10.3}",
+                format!("{}x{}", config.window_size(), config.num_windows()),
+                config.max_input_bits(),
+                len,
+                report.cs.num_constraints(),
+                1.0 / elapsed,
+                len as f64 / elapsed / (1024.0 * 1024.0),
+            );
+        }
+    }
+
+    // Both paths reject an input one byte too long
+    let config = PedersenWindowConfig::default();
+    let too_long = vec![0; config.max_input_bits() / 8 + 1];
+    let params = pedersen::CRH::<JubJub, Window128x4>::setup(&mut ark_std::test_rng()).unwrap();
+    let err = pedersen_evaluate::<Window128x4>(&params, &too_long).unwrap_err();
+    println!("native: {}", err);
+
+    let cs = ConstraintSystem::<ConstraintF>::new_ref();
+    let params_var = pedersen::constraints::CRHParametersVar::<JubJub, EdwardsVar>::new_constant(
+        ark_relations::ns!(cs, "CRH Parameters"),
+        &params,
+    )?;
+    let input_var = UInt8::new_witness_vec(ark_relations::ns!(cs, "input"), &too_long)?;
+    let err = pedersen_evaluate_gadget::<Window128x4>(&params_var, &input_var).err().unwrap();
+    println!("gadget: {}", err);
+
+    Ok(())
+}
+
+// Bowe-Hopwood windows count 3-bit chunks: 63 * 8 * 3 = 1512 input bits
+#[derive(Clone)]
+pub struct BoweHopwoodWindow;
+impl pedersen::Window for BoweHopwoodWindow {
+    const WINDOW_SIZE: usize = 63;
+    const NUM_WINDOWS: usize = 8;
+}
+
+type BoweHopwoodCRH = bowe_hopwood::CRH<EdwardsConfig, BoweHopwoodWindow>;
+type BoweHopwoodCRHGadget = bowe_hopwood::constraints::CRHGadget<EdwardsConfig, FpVar<ConstraintF>>;
+
+// Returns the number of constraints of hashing `input` with Bowe-Hopwood, checked against the native hash
+fn run_bowe_hopwood(params: &bowe_hopwood::Parameters<EdwardsConfig>, input: &[u8]) -> Result<usize, SynthesisError> {
+    let cs = ConstraintSystem::<ConstraintF>::new_ref();
+    cs.set_optimization_goal(OptimizationGoal::Constraints);
+
+    let hash_output = BoweHopwoodCRH::evaluate(params, input).unwrap();
+
+    let parameters_var = bowe_hopwood::constraints::ParametersVar::<EdwardsConfig, BoweHopwoodWindow>::new_constant(
+        ark_relations::ns!(cs, "CRH Parameters"),
+        params,
+    )?;
+    let input_var = UInt8::new_witness_vec(ark_relations::ns!(cs, "input"), input)?;
+    let output_var = FpVar::new_input(ark_relations::ns!(cs, "output"), || Ok(hash_output))?;
+    let result_var = BoweHopwoodCRHGadget::evaluate(&parameters_var, &input_var)?;
+
+    assert_eq!(result_var.value()?, hash_output);
+    result_var.enforce_equal(&output_var)?;
 
     assert!(cs.is_satisfied()?);
 
     cs.finalize();
 
-    print_cs_details(cs);
-    
+    Ok(cs.num_constraints())
+}
+
+// The output is only the x coordinate of the resulting Edwards point
+pub fn test_bowe_hopwood_crh() -> Result<(), SynthesisError> {
+    println!("\n### Running test_bowe_hopwood_crh()...");
+
+    let mut rng = ark_std::test_rng();
+
+    let params = BoweHopwoodCRH::setup(&mut rng).unwrap();
+
+    // Native and gadget agree on random inputs of every length up to 128 bytes
+    // (the gadget cannot hash the empty input)
+    for len in 1..=128 {
+        let input: Vec<u8> = (0..len).map(|_| u8::rand(&mut rng)).collect();
+        run_bowe_hopwood(&params, &input)?;
+    }
+
+    // A wrong output does not satisfy the circuit
+    let cs = ConstraintSystem::<ConstraintF>::new_ref();
+    let parameters_var = bowe_hopwood::constraints::ParametersVar::<EdwardsConfig, BoweHopwoodWindow>::new_constant(
+        ark_relations::ns!(cs, "CRH Parameters"),
+        &params,
+    )?;
+    let input_var = UInt8::new_witness_vec(ark_relations::ns!(cs, "input"), b"hello")?;
+    let output_var = FpVar::new_input(
+        ark_relations::ns!(cs, "output"),
+        || Ok(BoweHopwoodCRH::evaluate(&params, b"hellp".as_slice()).unwrap()),
+    )?;
+    BoweHopwoodCRHGadget::evaluate(&parameters_var, &input_var)?.enforce_equal(&output_var)?;
+    assert!(!cs.is_satisfied()?);
+
+    // Side by side with plain Pedersen (128x8 windows, 1024 input bits)
+    println!("{:>8} | {:>12} | {:>14}", "bytes", "pedersen", "bowe-hopwood");
+    for len in [16, 32, 64, 128] {
+        let input = vec![0xab; len];
+
+        let pedersen_constraints = PedersenWindowConfig::W128x8
+            .run(&input, OptimizationGoal::Constraints)
+            .unwrap()
+            .cs
+            .num_constraints();
+        let bowe_hopwood_constraints = run_bowe_hopwood(&params, &input)?;
+
+        println!("{:>8} | {:>12} | {:>14}", len, pedersen_constraints, bowe_hopwood_constraints);
+    }
+
+    Ok(())
+}
+
+
+pub type PoseidonCRH = poseidon::CRH<ConstraintF>;
+pub type PoseidonCRHGadget = poseidon::constraints::CRHGadget<ConstraintF>;
+pub type PoseidonCRHParametersVar = poseidon::constraints::CRHParametersVar<ConstraintF>;
+
+// Returns the number of constraints of hashing `input` with Poseidon, checked against the native hash
+fn run_poseidon(params: &PoseidonConfig<ConstraintF>, input: &[ConstraintF]) -> Result<usize, SynthesisError> {
+    let cs = ConstraintSystem::<ConstraintF>::new_ref();
+    cs.set_optimization_goal(OptimizationGoal::Constraints);
+
+    let hash_output = PoseidonCRH::evaluate(params, input).unwrap();
+
+    let parameters_var = PoseidonCRHParametersVar::new_constant(ark_relations::ns!(cs, "CRH Parameters"), params)?;
+    let input_var = Vec::<FpVar<ConstraintF>>::new_witness(ark_relations::ns!(cs, "input"), || Ok(input))?;
+    let output_var = FpVar::new_input(ark_relations::ns!(cs, "output"), || Ok(hash_output))?;
+    let result_var = PoseidonCRHGadget::evaluate(&parameters_var, &input_var)?;
+
+    assert_eq!(result_var.value()?, hash_output);
+    result_var.enforce_equal(&output_var)?;
+
+    assert!(cs.is_satisfied()?);
+
+    cs.finalize();
+
+    Ok(cs.num_constraints())
+}
+
+// Same, for a byte input packed into field elements (31 bytes each) as ToConstraintField does
+fn run_poseidon_bytes(params: &PoseidonConfig<ConstraintF>, input: &[u8]) -> Result<usize, SynthesisError> {
+    let cs = ConstraintSystem::<ConstraintF>::new_ref();
+    cs.set_optimization_goal(OptimizationGoal::Constraints);
+
+    let hash_output = PoseidonCRH::evaluate(params, input.to_field_elements().unwrap().as_slice()).unwrap();
+
+    let parameters_var = PoseidonCRHParametersVar::new_constant(ark_relations::ns!(cs, "CRH Parameters"), params)?;
+    let input_var = UInt8::new_witness_vec(ark_relations::ns!(cs, "input"), input)?;
+    let output_var = FpVar::new_input(ark_relations::ns!(cs, "output"), || Ok(hash_output))?;
+    let result_var = PoseidonCRHGadget::evaluate(&parameters_var, &input_var.to_constraint_field()?)?;
+
+    result_var.enforce_equal(&output_var)?;
+
+    assert!(cs.is_satisfied()?);
+
+    cs.finalize();
+
+    Ok(cs.num_constraints())
+}
+
+pub fn test_poseidon_crh() -> Result<(), SynthesisError> {
+    println!("\n### Running test_poseidon_crh()...");
+
+    let mut rng = ark_std::test_rng();
+
+    // State width = rate + 1
+    println!("{:>6} | {:>8} | {:>12}", "width", "elements", "constraints");
+    for rate in [2, 3, 4, 8] {
+        let params = poseidon_parameters::<ConstraintF>(rate);
+
+        for len in [1, 2, 4, 8, 16] {
+            let input: Vec<ConstraintF> = (0..len).map(|_| ConstraintF::rand(&mut rng)).collect();
+            let constraints = run_poseidon(&params, &input)?;
+            println!("{:>6} | {:>8} | {:>12}", rate + 1, len, constraints);
+        }
+    }
+
+    // A wrong output does not satisfy the circuit
+    let params = poseidon_parameters::<ConstraintF>(2);
+    let input = [ConstraintF::from(1u64), ConstraintF::from(2u64)];
+
+    let cs = ConstraintSystem::<ConstraintF>::new_ref();
+    let parameters_var = PoseidonCRHParametersVar::new_constant(ark_relations::ns!(cs, "CRH Parameters"), &params)?;
+    let input_var = Vec::<FpVar<ConstraintF>>::new_witness(ark_relations::ns!(cs, "input"), || Ok(input))?;
+    let output_var = FpVar::new_input(
+        ark_relations::ns!(cs, "output"),
+        || Ok(PoseidonCRH::evaluate(&params, [ConstraintF::from(2u64), ConstraintF::from(1u64)]).unwrap()),
+    )?;
+    PoseidonCRHGadget::evaluate(&parameters_var, &input_var)?.enforce_equal(&output_var)?;
+    assert!(!cs.is_satisfied()?);
+
     Ok(())
-}
\ No newline at end of file
+}
+
+// Constraint cost of every CRH gadget on the same byte inputs
+pub fn test_crh_costs() -> Result<(), SynthesisError> {
+    println!("\n### Running test_crh_costs()...");
+
+    let mut rng = ark_std::test_rng();
+
+    let bowe_hopwood_params = BoweHopwoodCRH::setup(&mut rng).unwrap();
+    let poseidon_params = poseidon_parameters::<ConstraintF>(2);
+
+    println!(
+        "{:>8} | {:>10} | {:>10} | {:>14} | {:>10}",
+        "bytes", "sha256", "pedersen", "bowe-hopwood", "poseidon",
+    );
+    for len in [16, 32, 64, 128] {
+        let input = vec![0xab; len];
+
+        let cs = ConstraintSystem::<ConstraintF>::new_ref();
+        cs.set_optimization_goal(OptimizationGoal::Constraints);
+        let input_var = UInt8::new_witness_vec(ark_relations::ns!(cs, "input"), &input)?;
+        Sha256Gadget::evaluate(&UnitVar::default(), &input_var)?;
+        let sha256_constraints = cs.num_constraints();
+
+        let pedersen_constraints = PedersenWindowConfig::W128x8
+            .run(&input, OptimizationGoal::Constraints)
+            .unwrap()
+            .cs
+            .num_constraints();
+
+        println!(
+            "{:>8} | {:>10} | {:>10} | {:>14} | {:>10}",
+            len,
+            sha256_constraints,
+            pedersen_constraints,
+            run_bowe_hopwood(&bowe_hopwood_params, &input)?,
+            run_poseidon_bytes(&poseidon_params, &input)?,
+        );
+    }
+
+    Ok(())
+}
+
+// MiMC gadgets over FpVar, mirroring utils::mimc. The round constants are circuit constants.
+pub fn mimc_cipher_gadget<F: PrimeField>(
+    params: &MimcParameters<F>,
+    x: &FpVar<F>,
+    k: &FpVar<F>,
+) -> Result<FpVar<F>, SynthesisError> {
+    let mut x = x.clone();
+    for c in params.round_constants.iter() {
+        x = (x + k + *c).pow_by_constant([params.exponent])?;
+    }
+    Ok(x + k)
+}
+
+pub fn mimc_hash_gadget<F: PrimeField>(
+    params: &MimcParameters<F>,
+    input: &[FpVar<F>],
+) -> Result<FpVar<F>, SynthesisError> {
+    let mut h = FpVar::zero();
+    for m in input {
+        h = mimc_cipher_gadget(params, m, &h)? + &h + m;
+    }
+    Ok(h)
+}
+
+pub fn mimc_feistel_gadget<F: PrimeField>(
+    params: &MimcParameters<F>,
+    x_l: &FpVar<F>,
+    x_r: &FpVar<F>,
+    k: &FpVar<F>,
+) -> Result<(FpVar<F>, FpVar<F>), SynthesisError> {
+    let (mut x_l, mut x_r) = (x_l.clone(), x_r.clone());
+    let rounds = params.rounds();
+    for (i, c) in params.round_constants.iter().enumerate() {
+        let t = &x_r + (&x_l + k + *c).pow_by_constant([params.exponent])?;
+        if i < rounds - 1 {
+            (x_l, x_r) = (t, x_l);
+        } else {
+            x_r = t;
+        }
+    }
+    Ok((x_l, x_r))
+}
+
+pub fn mimc_feistel_hash_gadget<F: PrimeField>(
+    params: &MimcParameters<F>,
+    input: &[FpVar<F>],
+) -> Result<FpVar<F>, SynthesisError> {
+    let (mut x_l, mut x_r) = (FpVar::zero(), FpVar::zero());
+    for m in input {
+        (x_l, x_r) = mimc_feistel_gadget(params, &(x_l + m), &x_r, &FpVar::zero())?;
+    }
+    Ok(x_l)
+}
+
+#[derive(Clone, Copy, Debug)]
+enum MimcMode {
+    Cipher,
+    Feistel,
+}
+
+// Returns the number of constraints of hashing `input`, checked against the native hash
+fn run_mimc(params: &MimcParameters<Fr>, mode: MimcMode, input: &[Fr]) -> Result<usize, SynthesisError> {
+    let cs = ConstraintSystem::<Fr>::new_ref();
+    cs.set_optimization_goal(OptimizationGoal::Constraints);
+
+    let hash_output = match mode {
+        MimcMode::Cipher => mimc_hash(params, input),
+        MimcMode::Feistel => mimc_feistel_hash(params, input),
+    };
+
+    let input_var = Vec::<FpVar<Fr>>::new_witness(ark_relations::ns!(cs, "input"), || Ok(input))?;
+    let output_var = FpVar::new_input(ark_relations::ns!(cs, "output"), || Ok(hash_output))?;
+    let result_var = match mode {
+        MimcMode::Cipher => mimc_hash_gadget(params, &input_var)?,
+        MimcMode::Feistel => mimc_feistel_hash_gadget(params, &input_var)?,
+    };
+
+    assert_eq!(result_var.value()?, hash_output);
+    result_var.enforce_equal(&output_var)?;
+
+    assert!(cs.is_satisfied()?);
+
+    cs.finalize();
+
+    Ok(cs.num_constraints())
+}
+
+pub fn test_mimc_crh() -> Result<(), SynthesisError> {
+    println!("\n### Running test_mimc_crh()...");
+
+    let mut rng = ark_std::test_rng();
+
+    // x^7 only in the Feistel mode: it is not a permutation of Fr
+    let variants = [
+        ("mimc-11", MimcMode::Cipher, MimcParameters::<Fr>::cipher(MIMC_SEED, 11)),
+        ("feistel-7", MimcMode::Feistel, MimcParameters::<Fr>::feistel(MIMC_SEED, 7)),
+        ("feistel-11", MimcMode::Feistel, MimcParameters::<Fr>::feistel(MIMC_SEED, 11)),
+    ];
+    assert!(!variants[1].2.is_permutation());
+
+    // The constants only depend on the seed
+    assert_eq!(MimcParameters::<Fr>::new(MIMC_SEED, 7, variants[0].2.rounds()).round_constants, variants[0].2.round_constants);
+    assert_ne!(MimcParameters::<Fr>::cipher(b"other seed", 11).round_constants, variants[0].2.round_constants);
+
+    println!(
+        "{:>10} | {:>7} | {:>14} | {:>8} | {:>12}",
+        "variant", "rounds", "x^e bijective", "elements", "constraints",
+    );
+    for (name, mode, params) in variants.iter() {
+        for len in [1, 2, 4, 8] {
+            let input: Vec<Fr> = (0..len).map(|_| Fr::rand(&mut rng)).collect();
+            let constraints = run_mimc(params, *mode, &input)?;
+            println!(
+                "{:>10} | {:>7} | {:>14} | {:>8} | {:>12}",
+                name, params.rounds(), params.is_permutation(), len, constraints,
+            );
+        }
+    }
+
+    // The Feistel network is invertible whatever the exponent: undo the rounds natively
+    let params = &variants[1].2;
+    let (x_l, x_r) = (Fr::rand(&mut rng), Fr::rand(&mut rng));
+    let (mut y_l, mut y_r) = mimc_feistel(params, x_l, x_r, Fr::from(0u64));
+    for (i, c) in params.round_constants.iter().enumerate().rev() {
+        if i < params.rounds() - 1 {
+            (y_l, y_r) = (y_r, y_l);
+        }
+        y_r -= (y_l + c).pow([params.exponent]);
+    }
+    assert_eq!((y_l, y_r), (x_l, x_r));
+
+    // A wrong output does not satisfy the circuit
+    for (_, mode, params) in variants.iter() {
+        let input = [Fr::from(1u64), Fr::from(2u64)];
+        let swapped = [Fr::from(2u64), Fr::from(1u64)];
+
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let input_var = Vec::<FpVar<Fr>>::new_witness(ark_relations::ns!(cs, "input"), || Ok(input))?;
+        let (result_var, wrong_output) = match mode {
+            MimcMode::Cipher => (mimc_hash_gadget(params, &input_var)?, mimc_hash(params, &swapped)),
+            MimcMode::Feistel => (mimc_feistel_hash_gadget(params, &input_var)?, mimc_feistel_hash(params, &swapped)),
+        };
+        let output_var = FpVar::new_input(ark_relations::ns!(cs, "output"), || Ok(wrong_output))?;
+        result_var.enforce_equal(&output_var)?;
+        assert!(!cs.is_satisfied()?);
+    }
+
+    Ok(())
+}
+
+// Windows for byte inputs of up to 1 KiB (8192 bits)
+pedersen_window!(Window256x32, 256, 32);
+
+// 63 * 44 * 3 = 8316 input bits
+#[derive(Clone)]
+pub struct BoweHopwoodWindow1K;
+impl pedersen::Window for BoweHopwoodWindow1K {
+    const WINDOW_SIZE: usize = 63;
+    const NUM_WINDOWS: usize = 44;
+}
+
+// Byte-oriented view of every CRH above, so that they can be compared on the same inputs.
+// Digests are returned as bytes, in the encoding of ToBytesGadget for the output variable.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HashFunction {
+    Sha256,
+    Pedersen,
+    BoweHopwood,
+    Blake2s,
+    Poseidon,
+}
+
+#[derive(Clone)]
+pub struct HashParameters {
+    pub pedersen: pedersen::Parameters<JubJub>,
+    pub bowe_hopwood: bowe_hopwood::Parameters<EdwardsConfig>,
+    pub poseidon: PoseidonConfig<ConstraintF>,
+}
+
+impl HashParameters {
+    pub fn setup<R: Rng>(rng: &mut R) -> HashParameters {
+        HashParameters {
+            pedersen: pedersen::CRH::<JubJub, Window256x32>::setup(rng).unwrap(),
+            bowe_hopwood: bowe_hopwood::CRH::<EdwardsConfig, BoweHopwoodWindow1K>::setup(rng).unwrap(),
+            poseidon: poseidon_parameters::<ConstraintF>(2),
+        }
+    }
+}
+
+impl HashFunction {
+    pub const ALL: [HashFunction; 5] = [
+        HashFunction::Sha256,
+        HashFunction::Pedersen,
+        HashFunction::BoweHopwood,
+        HashFunction::Blake2s,
+        HashFunction::Poseidon,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            HashFunction::Sha256 => "sha256",
+            HashFunction::Pedersen => "pedersen",
+            HashFunction::BoweHopwood => "bowe-hopwood",
+            HashFunction::Blake2s => "blake2s",
+            HashFunction::Poseidon => "poseidon",
+        }
+    }
+
+    pub fn evaluate(&self, params: &HashParameters, input: &[u8]) -> Result<Vec<u8>, Error> {
+        let digest = match self {
+            HashFunction::Sha256 => Sha256::evaluate(&(), input)?,
+            HashFunction::Pedersen => {
+                let output = pedersen_evaluate::<Window256x32>(&params.pedersen, input)?;
+                point_to_bytes(&output)
+            }
+            // Bowe-Hopwood outputs only the x-coordinate
+            HashFunction::BoweHopwood => {
+                bowe_hopwood::CRH::<EdwardsConfig, BoweHopwoodWindow1K>::evaluate(&params.bowe_hopwood, input)?
+                    .into_bigint()
+                    .to_bytes_le()
+            }
+            HashFunction::Blake2s => Blake2s256::digest(input).to_vec(),
+            HashFunction::Poseidon => {
+                let elements = input.to_field_elements().ok_or(CryptoError::IncorrectInputLength(input.len()))?;
+                PoseidonCRH::evaluate(&params.poseidon, elements)?.into_bigint().to_bytes_le()
+            }
+        };
+        Ok(digest)
+    }
+
+    pub fn evaluate_gadget(
+        &self,
+        params: &HashParameters,
+        input: &[UInt8<ConstraintF>],
+    ) -> Result<Vec<UInt8<ConstraintF>>, Error> {
+        let cs = input.cs();
+        let digest = match self {
+            HashFunction::Sha256 => Sha256Gadget::evaluate(&UnitVar::default(), input)?.to_bytes()?,
+            HashFunction::Pedersen => {
+                let params_var = pedersen::constraints::CRHParametersVar::new_constant(
+                    ark_relations::ns!(cs, "CRH Parameters"),
+                    &params.pedersen,
+                )?;
+                // Inputs longer than the windows are an error, not an unsatisfiable circuit
+                pedersen_evaluate_gadget::<Window256x32>(&params_var, input)?.to_bytes()?
+            }
+            HashFunction::BoweHopwood => {
+                let params_var = bowe_hopwood::constraints::ParametersVar::<EdwardsConfig, BoweHopwoodWindow1K>::new_constant(
+                    ark_relations::ns!(cs, "CRH Parameters"),
+                    &params.bowe_hopwood,
+                )?;
+                BoweHopwoodCRHGadget::evaluate(&params_var, input)?.to_bytes()?
+            }
+            HashFunction::Blake2s => blake2s_gadget(input)?,
+            HashFunction::Poseidon => {
+                let params_var = PoseidonCRHParametersVar::new_constant(
+                    ark_relations::ns!(cs, "CRH Parameters"),
+                    &params.poseidon,
+                )?;
+                PoseidonCRHGadget::evaluate(&params_var, &input.to_constraint_field()?)?.to_bytes()?
+            }
+        };
+        Ok(digest)
+    }
+}
+
+// Same encoding as EdwardsVar::to_bytes: x then y, little-endian
+fn point_to_bytes(point: &EdwardsAffine) -> Vec<u8> {
+    let mut bytes = point.x.into_bigint().to_bytes_le();
+    bytes.extend(point.y.into_bigint().to_bytes_le());
+    bytes
+}
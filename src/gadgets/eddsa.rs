@@ -0,0 +1,223 @@
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+
+use ark_relations::r1cs::{ConstraintSystem, ConstraintSystemRef, Namespace, SynthesisError, OptimizationGoal};
+use ark_bls12_377::Fr;
+use ark_crypto_primitives::crh::{
+    sha256::constraints::{Sha256Gadget, UnitVar},
+    CRHSchemeGadget,
+};
+use ark_ec::{AffineRepr, CurveGroup};
+use ark_ed_on_bls12_377::{
+    EdwardsAffine,
+    Fr as ScalarF,
+    constraints::EdwardsVar,
+};
+use ark_ff::{BigInteger, PrimeField};
+use ark_r1cs_std::{
+    alloc::{AllocVar, AllocationMode},
+    boolean::Boolean,
+    eq::EqGadget,
+    fields::{fp::FpVar, FieldVar},
+    groups::CurveVar,
+    uint8::UInt8,
+    R1CSVar,
+    ToBitsGadget,
+    ToBytesGadget,
+    ToConstraintFieldGadget,
+};
+
+use crate::gadgets::{PoseidonCRHGadget, PoseidonCRHParametersVar};
+use crate::gadgets::schnorr::{compressed_point_gadget, fixed_base_mul_gadget};
+use crate::utils::eddsa::{eddsa_verify, EddsaHash, EddsaKeyPair, EddsaSignature, COFACTOR_BITS};
+use crate::utils::poseidon::poseidon_parameters;
+
+// In-circuit verification of the EdDSA signatures of utils::eddsa, with the challenge hash
+// (SHA-256 or Poseidon from gadgets::crh) computed in-circuit.
+
+pub enum EddsaHashVar {
+    Sha256,
+    Poseidon(PoseidonCRHParametersVar),
+}
+
+impl EddsaHashVar {
+    pub fn new_constant(cs: ConstraintSystemRef<Fr>, hash: &EddsaHash) -> Result<Self, SynthesisError> {
+        Ok(match hash {
+            EddsaHash::Sha256 => EddsaHashVar::Sha256,
+            EddsaHash::Poseidon(params) => EddsaHashVar::Poseidon(
+                PoseidonCRHParametersVar::new_constant(ark_relations::ns!(cs, "CRH Parameters"), params)?,
+            ),
+        })
+    }
+
+    // The challenge as little-endian bits: 256 for SHA-256, 253 for Poseidon
+    pub fn challenge(&self, r: &EdwardsVar, pk: &EdwardsVar, msg: &[UInt8<Fr>]) -> Result<Vec<Boolean<Fr>>, SynthesisError> {
+        match self {
+            EddsaHashVar::Sha256 => {
+                let mut input = compressed_point_gadget(r)?;
+                input.extend(compressed_point_gadget(pk)?);
+                input.extend_from_slice(msg);
+                Sha256Gadget::<Fr>::evaluate(&UnitVar::default(), &input)?.to_bytes()?.to_bits_le()
+            }
+            EddsaHashVar::Poseidon(params) => {
+                let mut input = vec![r.x.clone(), r.y.clone(), pk.x.clone(), pk.y.clone()];
+                input.extend(msg.to_constraint_field()?);
+                PoseidonCRHGadget::evaluate(params, &input)?.to_bits_le()
+            }
+        }
+    }
+}
+
+// R as a point, S as its bits (little endian)
+#[derive(Clone)]
+pub struct EddsaSignatureVar {
+    pub r: EdwardsVar,
+    pub s: Vec<Boolean<Fr>>,
+}
+
+impl AllocVar<EddsaSignature, Fr> for EddsaSignatureVar {
+    fn new_variable<T: Borrow<EddsaSignature>>(
+        cs: impl Into<Namespace<Fr>>,
+        f: impl FnOnce() -> Result<T, SynthesisError>,
+        mode: AllocationMode,
+    ) -> Result<Self, SynthesisError> {
+        let ns = cs.into();
+        let cs = ns.cs();
+        let signature = f().map(|signature| signature.borrow().clone());
+
+        let r = EdwardsVar::new_variable(
+            ark_relations::ns!(cs, "r"),
+            || signature.as_ref().map(|signature| signature.r).map_err(|e| *e),
+            mode,
+        )?;
+        let s_bits = signature.map(|signature| {
+            let mut bits = signature.s.into_bigint().to_bits_le();
+            bits.truncate(ScalarF::MODULUS_BIT_SIZE as usize);
+            bits
+        });
+        let s = Vec::<Boolean<Fr>>::new_variable(ark_relations::ns!(cs, "s"), || s_bits, mode)?;
+
+        Ok(EddsaSignatureVar { r, s })
+    }
+}
+
+// Returns whether S < l and [4][S]B == [4](R + [k]A), k = H'(R, A, M)
+pub fn eddsa_verify_gadget(
+    hash: &EddsaHashVar,
+    pk: &EdwardsVar,
+    msg: &[UInt8<Fr>],
+    signature: &EddsaSignatureVar,
+) -> Result<Boolean<Fr>, SynthesisError> {
+    // S has fewer bits than (p - 1) / 2, as required by is_cmp
+    let order = FpVar::constant(Fr::from_le_bytes_mod_order(&ScalarF::MODULUS.to_bytes_le()));
+    let s_is_canonical = Boolean::le_bits_to_fp_var(&signature.s)?.is_cmp(&order, Ordering::Less, false)?;
+
+    let k = hash.challenge(&signature.r, pk, msg)?;
+    let generator = EdwardsVar::constant(EdwardsAffine::generator().into_group());
+    let mut lhs = fixed_base_mul_gadget(&generator, &signature.s)?;
+    let mut rhs = signature.r.clone() + pk.scalar_mul_le(k.iter())?;
+    for _ in 0..COFACTOR_BITS {
+        lhs = lhs.double()?;
+        rhs = rhs.double()?;
+    }
+
+    lhs.is_eq(&rhs)?.and(&s_is_canonical)
+}
+
+// Returns (verified in-circuit, constraints)
+fn run_eddsa(hash: &EddsaHash, pk: &EdwardsAffine, msg: &[u8], signature: &EddsaSignatureVarValue) -> Result<(bool, usize), SynthesisError> {
+    let cs = ConstraintSystem::<Fr>::new_ref();
+    cs.set_optimization_goal(OptimizationGoal::Constraints);
+
+    let hash_var = EddsaHashVar::new_constant(cs.clone(), hash)?;
+    let pk_var = EdwardsVar::new_input(ark_relations::ns!(cs, "pk"), || Ok(*pk))?;
+    let msg_var = UInt8::new_input_vec(ark_relations::ns!(cs, "msg"), msg)?;
+    let signature_var = match signature {
+        EddsaSignatureVarValue::Signature(signature) => {
+            EddsaSignatureVar::new_witness(ark_relations::ns!(cs, "signature"), || Ok(signature))?
+        }
+        EddsaSignatureVarValue::Bits(r, s) => EddsaSignatureVar {
+            r: EdwardsVar::new_witness(ark_relations::ns!(cs, "r"), || Ok(*r))?,
+            s: Vec::<Boolean<Fr>>::new_witness(ark_relations::ns!(cs, "s"), || Ok(s.as_slice()))?,
+        },
+    };
+
+    let verified = eddsa_verify_gadget(&hash_var, &pk_var, &msg_var, &signature_var)?;
+    assert!(cs.is_satisfied()?);
+
+    cs.finalize();
+
+    Ok((verified.value()?, cs.num_constraints()))
+}
+
+// A signature, or raw (R, bits of S) for non-canonical S
+enum EddsaSignatureVarValue {
+    Signature(EddsaSignature),
+    Bits(EdwardsAffine, Vec<bool>),
+}
+
+pub fn test_eddsa_signature() -> Result<(), SynthesisError> {
+    println!("\n### Running test_eddsa_signature()...");
+
+    let mut rng = ark_std::test_rng();
+
+    let key_pair = EddsaKeyPair::generate(&mut rng);
+    let other_key_pair = EddsaKeyPair::generate(&mut rng);
+    let hashes = [EddsaHash::Sha256, EddsaHash::Poseidon(poseidon_parameters::<Fr>(2))];
+
+    println!("{:>10} | {:>10} | {:>12}", "hash", "msg bytes", "constraints");
+    for hash in hashes.iter() {
+        for len in [32, 64, 128] {
+            let msg: Vec<u8> = (0..len as u8).collect();
+            let signature = key_pair.sign(hash, &msg);
+            assert!(eddsa_verify(hash, &key_pair.pk, &msg, &signature));
+            // Deterministic signatures
+            assert_eq!(key_pair.sign(hash, &msg), signature);
+
+            let (verified, constraints) = run_eddsa(hash, &key_pair.pk, &msg, &EddsaSignatureVarValue::Signature(signature))?;
+            assert!(verified);
+            println!("{:>10} | {:>10} | {:>12}", hash.name(), len, constraints);
+        }
+    }
+
+    for hash in hashes.iter() {
+        let msg = b"ed_on_bls12_377 EdDSA".to_vec();
+        let signature = key_pair.sign(hash, &msg);
+        let mut other_msg = msg.clone();
+        other_msg[0] ^= 1;
+
+        let forged_s = EddsaSignature { s: signature.s + ScalarF::from(1u64), ..signature.clone() };
+        let forged_r = EddsaSignature { r: (signature.r + EdwardsAffine::generator()).into_affine(), ..signature.clone() };
+        let forgeries = [
+            (key_pair.pk, other_msg, signature.clone()),
+            (other_key_pair.pk, msg.clone(), signature.clone()),
+            (key_pair.pk, msg.clone(), forged_s),
+            (key_pair.pk, msg.clone(), forged_r),
+            (key_pair.pk, msg.clone(), other_key_pair.sign(hash, &msg)),
+        ];
+        for (pk, msg, signature) in forgeries {
+            assert!(!eddsa_verify(hash, &pk, &msg, &signature));
+            assert!(!run_eddsa(hash, &pk, &msg, &EddsaSignatureVarValue::Signature(signature))?.0);
+        }
+
+        // S + l satisfies the group equation but is not canonical (it must fit in 251 bits)
+        let (msg, signature) = (0u8..)
+            .map(|i| {
+                let msg = vec![i; 32];
+                let signature = key_pair.sign(hash, &msg);
+                (msg, signature)
+            })
+            .find(|(_, signature)| {
+                let mut s = signature.s.into_bigint();
+                !s.add_with_carry(&ScalarF::MODULUS) && s.num_bits() <= ScalarF::MODULUS_BIT_SIZE
+            })
+            .unwrap();
+        let mut s = signature.s.into_bigint();
+        s.add_with_carry(&ScalarF::MODULUS);
+        let mut bits = s.to_bits_le();
+        bits.truncate(ScalarF::MODULUS_BIT_SIZE as usize);
+        assert!(!run_eddsa(hash, &key_pair.pk, &msg, &EddsaSignatureVarValue::Bits(signature.r, bits))?.0);
+    }
+
+    Ok(())
+}
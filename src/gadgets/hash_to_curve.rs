@@ -0,0 +1,186 @@
+use ark_relations::r1cs::{ConstraintSystem, SynthesisError, OptimizationGoal};
+use ark_bls12_377::{
+    g1::Config as G1Config,
+    constraints::G1Var,
+    Config,
+    Fq,
+    G1Affine,
+};
+use ark_crypto_primitives::crh::{
+    sha256::constraints::{Sha256Gadget, UnitVar},
+    CRHSchemeGadget,
+};
+use ark_r1cs_std::{
+    alloc::AllocVar,
+    bits::{boolean::Boolean, uint8::UInt8, ToBitsGadget},
+    eq::EqGadget,
+    fields::{fp::FpVar, FieldVar},
+    groups::CurveVar,
+    R1CSVar,
+    ToBytesGadget,
+};
+use ark_ec::{bls12::Bls12Config, short_weierstrass::SWCurveConfig, AffineRepr};
+use ark_ff::{BigInteger, BitIteratorBE, FftField, Field, PrimeField};
+
+use crate::utils::bls::{hash_to_g1, try_and_increment, HASH_TO_G1_MAX_TRIES};
+use crate::utils::print_cs_details;
+
+// In-circuit version of utils::bls::hash_to_g1 (try-and-increment), over BW6-761 Fr = BLS12-377 Fq.
+// Each step mirrors the native implementation so both give the same point bit for bit.
+pub fn hash_to_g1_gadget(msg: &[UInt8<Fq>]) -> Result<G1Var, SynthesisError> {
+    let x_0 = hash_to_field(msg)?;
+    let point = map_to_curve(&x_0)?;
+    clear_cofactor(&point)
+}
+
+// x_0 = SHA256(msg) read as a little-endian integer (256 bits, so no reduction is needed)
+pub fn hash_to_field(msg: &[UInt8<Fq>]) -> Result<FpVar<Fq>, SynthesisError> {
+    let digest = Sha256Gadget::<Fq>::evaluate(&UnitVar::default(), msg)?;
+    let mut bits = Vec::new();
+    for byte in digest.to_bytes()? {
+        bits.extend(byte.to_bits_le()?);
+    }
+    Boolean::le_bits_to_fp_var(&bits)
+}
+
+// Unrolls HASH_TO_G1_MAX_TRIES attempts x_i = x_0 + i. For every attempt the prover shows either
+// a root of f_i = x_i^3 + b or a root of n * f_i (n a fixed non-residue), so that the selected
+// attempt is really the first one where f_i is a square, as in the native loop.
+pub fn map_to_curve(x_0: &FpVar<Fq>) -> Result<G1Var, SynthesisError> {
+    // e.g. the empty message: the whole hash is known at synthesis time
+    if x_0.is_constant() {
+        let (x, y) = try_and_increment(x_0.value()?).ok_or(SynthesisError::Unsatisfiable)?;
+        return Ok(G1Var::new(FpVar::constant(x), FpVar::constant(y), FpVar::one()));
+    }
+
+    let cs = x_0.cs();
+    let non_residue = Fq::GENERATOR;
+
+    let mut found = Boolean::FALSE;
+    let mut x = FpVar::zero();
+    let mut y = FpVar::zero();
+
+    for i in 0..HASH_TO_G1_MAX_TRIES {
+        let x_i = x_0 + Fq::from(i);
+        let f_i = x_i.square()? * &x_i + G1Config::COEFF_B;
+        let f_value = f_i.value();
+
+        let is_square = Boolean::new_witness(ark_relations::ns!(cs, "is_square"), || {
+            Ok(f_value?.sqrt().is_some())
+        })?;
+        let root = FpVar::new_witness(ark_relations::ns!(cs, "root"), || {
+            let f = f_value?;
+            Ok(f.sqrt().or_else(|| (f * non_residue).sqrt()).unwrap())
+        })?;
+        root.mul_equals(&root, &is_square.select(&f_i, &(&f_i * non_residue))?)?;
+
+        let take = is_square.and(&found.not())?;
+        x = take.select(&x_i, &x)?;
+        y = take.select(&root, &y)?;
+        found = found.or(&is_square)?;
+    }
+    found.enforce_equal(&Boolean::TRUE)?;
+
+    // Canonical root: y' = +-y with an even representative
+    let y_even = FpVar::new_witness(ark_relations::ns!(cs, "y"), || {
+        let y = y.value()?;
+        Ok(if y.into_bigint().is_odd() { -y } else { y })
+    })?;
+    (&y_even - &y).mul_equals(&(&y_even + &y), &FpVar::zero())?;
+    y_even.to_bits_le()?[0].enforce_equal(&Boolean::FALSE)?;
+
+    Ok(G1Var::new(x, y_even, FpVar::one()))
+}
+
+// Multiplication by the effective cofactor x - 1, as in g1::Config::clear_cofactor.
+// Double-and-add with the complete formulas, since the scalar is a constant.
+pub fn clear_cofactor(point: &G1Var) -> Result<G1Var, SynthesisError> {
+    // G1Var::value() asserts subgroup membership, so constants are cleared natively
+    if point.is_constant() {
+        let z_inv = point.z.value()?.inverse().ok_or(SynthesisError::DivisionByZero)?;
+        let affine = G1Affine::new_unchecked(point.x.value()? * z_inv, point.y.value()? * z_inv);
+        return Ok(G1Var::constant(affine.clear_cofactor().into_group()));
+    }
+
+    let h_eff = [<Config as Bls12Config>::X[0] - 1];
+
+    let mut result = point.clone();
+    for bit in BitIteratorBE::without_leading_zeros(h_eff).skip(1) {
+        result.double_in_place()?;
+        if bit {
+            result += point;
+        }
+    }
+    Ok(result)
+}
+
+pub fn test_hash_to_curve() -> Result<(), SynthesisError> {
+    println!("\n### Running test_hash_to_curve()...");
+
+    // Native and in-circuit hashes must agree on many messages
+    let mut messages: Vec<Vec<u8>> = (0..64).map(|i| format!("message {}", i).into_bytes()).collect();
+    messages.extend([0, 1, 55, 56, 63, 64, 65, 119, 120, 200].map(|len| vec![0xab; len]));
+
+    for msg in messages.iter() {
+        let cs = ConstraintSystem::<Fq>::new_ref();
+
+        let native = hash_to_g1(msg).unwrap();
+
+        let msg_var = UInt8::new_witness_vec(ark_relations::ns!(cs, "msg"), msg)?;
+        let output_var = G1Var::new_input(ark_relations::ns!(cs, "output"), || Ok(native))?;
+        let hash_var = hash_to_g1_gadget(&msg_var)?;
+
+        assert_eq!(hash_var.value()?, native);
+        hash_var.enforce_equal(&output_var)?;
+        assert!(cs.is_satisfied()?);
+    }
+    println!("{} messages hashed consistently", messages.len());
+
+    // The hash of another message does not satisfy the circuit
+    let cs = ConstraintSystem::<Fq>::new_ref();
+    let msg_var = UInt8::new_witness_vec(ark_relations::ns!(cs, "msg"), b"hello")?;
+    let output_var = G1Var::new_input(ark_relations::ns!(cs, "output"), || Ok(hash_to_g1(b"bye").unwrap()))?;
+    hash_to_g1_gadget(&msg_var)?.enforce_equal(&output_var)?;
+    assert!(!cs.is_satisfied()?);
+
+    // Constraint cost of every step per message length
+    println!(
+        "{:>8} | {:>14} | {:>14} | {:>14} | {:>12}",
+        "bytes", "hash_to_field", "map_to_curve", "clear_cofactor", "total",
+    );
+    for len in [0, 32, 64, 128, 256] {
+        let cs = ConstraintSystem::<Fq>::new_ref();
+        cs.set_optimization_goal(OptimizationGoal::Constraints);
+
+        let msg_var = UInt8::new_witness_vec(ark_relations::ns!(cs, "msg"), &vec![0xab; len])?;
+
+        let x_0 = hash_to_field(&msg_var)?;
+        let after_hash = cs.num_constraints();
+        let point = map_to_curve(&x_0)?;
+        let after_map = cs.num_constraints();
+        let hash_var = clear_cofactor(&point)?;
+        let total = cs.num_constraints();
+
+        assert_eq!(hash_var.value()?, hash_to_g1(&vec![0xab; len]).unwrap());
+        assert!(cs.is_satisfied()?);
+
+        println!(
+            "{:>8} | {:>14} | {:>14} | {:>14} | {:>12}",
+            len, after_hash, after_map - after_hash, total - after_map, total,
+        );
+    }
+
+    let cs = ConstraintSystem::<Fq>::new_ref();
+    cs.set_optimization_goal(OptimizationGoal::None);
+    let msg_var = UInt8::new_witness_vec(ark_relations::ns!(cs, "msg"), b"hello")?;
+    let hash_var = hash_to_g1_gadget(&msg_var)?;
+    let output_var = G1Var::new_input(ark_relations::ns!(cs, "output"), || Ok(hash_to_g1(b"hello").unwrap()))?;
+    hash_var.enforce_equal(&output_var)?;
+    assert!(cs.is_satisfied()?);
+
+    cs.finalize();
+
+    print_cs_details(cs);
+
+    Ok(())
+}
@@ -0,0 +1,182 @@
+use ark_relations::r1cs::{ConstraintSystem, SynthesisError, OptimizationGoal};
+use ark_bls12_377::Fr;
+use ark_crypto_primitives::crh::{
+    sha256::constraints::{Sha256Gadget, UnitVar},
+    CRHSchemeGadget,
+};
+use ark_ff::PrimeField;
+use ark_r1cs_std::{
+    bits::{boolean::Boolean, uint8::UInt8, ToBitsGadget},
+    eq::EqGadget,
+    R1CSVar,
+};
+use ark_std::UniformRand;
+
+use crate::utils::hex_to_bytes;
+use crate::utils::keccak::{keccak256, keccak256_pad, KECCAK256_RATE, ROTATION_OFFSETS, ROUND_CONSTANTS};
+use crate::utils::print_cs_details;
+
+// A 64-bit lane as little-endian bits
+type Lane<F> = Vec<Boolean<F>>;
+
+fn xor_lanes<F: PrimeField>(a: &Lane<F>, b: &Lane<F>) -> Result<Lane<F>, SynthesisError> {
+    a.iter().zip(b).map(|(a, b)| a.xor(b)).collect()
+}
+
+fn rotate_left<F: PrimeField>(lane: &Lane<F>, n: u32) -> Lane<F> {
+    let n = n as usize;
+    (0..64).map(|i| lane[(i + 64 - n) % 64].clone()).collect()
+}
+
+// In-circuit version of utils::keccak::keccak_f. Rho and pi only move bits around;
+// theta and chi cost one constraint per XOR/AND of non-constant bits.
+pub fn keccak_f_gadget<F: PrimeField>(state: &mut [Lane<F>]) -> Result<(), SynthesisError> {
+    assert_eq!(state.len(), 25);
+
+    for round_constant in ROUND_CONSTANTS {
+        // theta
+        let mut c = Vec::with_capacity(5);
+        for x in 0..5 {
+            let mut parity = state[x].clone();
+            for y in 1..5 {
+                parity = xor_lanes(&parity, &state[x + 5 * y])?;
+            }
+            c.push(parity);
+        }
+        for x in 0..5 {
+            let d = xor_lanes(&c[(x + 4) % 5], &rotate_left(&c[(x + 1) % 5], 1))?;
+            for y in 0..5 {
+                state[x + 5 * y] = xor_lanes(&state[x + 5 * y], &d)?;
+            }
+        }
+
+        // rho and pi
+        let mut b = vec![Vec::new(); 25];
+        for x in 0..5 {
+            for y in 0..5 {
+                b[y + 5 * ((2 * x + 3 * y) % 5)] = rotate_left(&state[x + 5 * y], ROTATION_OFFSETS[x + 5 * y]);
+            }
+        }
+
+        // chi
+        for x in 0..5 {
+            for y in 0..5 {
+                let lane: Result<Lane<F>, SynthesisError> = (0..64).map(|i| {
+                    let not_and = b[(x + 1) % 5 + 5 * y][i].not().and(&b[(x + 2) % 5 + 5 * y][i])?;
+                    b[x + 5 * y][i].xor(&not_and)
+                }).collect();
+                state[x + 5 * y] = lane?;
+            }
+        }
+
+        // iota: XOR with constant bits is free
+        state[0] = state[0].iter().enumerate().map(|(i, bit)| {
+            if (round_constant >> i) & 1 == 1 { bit.not() } else { bit.clone() }
+        }).collect();
+    }
+    Ok(())
+}
+
+// Keccak-256 sponge. The input length is known at synthesis time, so the padding is constant.
+pub fn keccak256_gadget<F: PrimeField>(input: &[UInt8<F>]) -> Result<Vec<UInt8<F>>, SynthesisError> {
+    let padding = keccak256_pad(&vec![0; input.len()])[input.len()..].to_vec();
+
+    let mut bits = Vec::new();
+    for byte in input.iter().cloned().chain(padding.into_iter().map(UInt8::constant)) {
+        bits.extend(byte.to_bits_le()?);
+    }
+
+    let mut state: Vec<Lane<F>> = vec![vec![Boolean::FALSE; 64]; 25];
+    for block in bits.chunks(KECCAK256_RATE * 8) {
+        for (lane, block_lane) in state.iter_mut().zip(block.chunks(64)) {
+            *lane = xor_lanes(lane, &block_lane.to_vec())?;
+        }
+        keccak_f_gadget(&mut state)?;
+    }
+
+    Ok(state[..4].iter().flat_map(|lane| lane.chunks(8).map(UInt8::from_bits_le)).collect())
+}
+
+pub fn test_keccak256() -> Result<(), SynthesisError> {
+    println!("\n### Running test_keccak256()...");
+
+    // Known answers of Keccak-256 (as used by Ethereum)
+    let vectors: [(&[u8], &str); 3] = [
+        (b"", "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"),
+        (b"abc", "4e03657aea45a94fc7d47ba826c8d667c0d1e6e33a64a036ec44f58fa12d6c45"),
+        (b"The quick brown fox jumps over the lazy dog", "4d741b6f1eb29cb2a9b9911c82f56fa8d73b04959d3d9d222895df6c0b28aa15"),
+    ];
+
+    for (msg, expected) in vectors {
+        let expected = hex_to_bytes(expected);
+        assert_eq!(keccak256(msg).to_vec(), expected);
+
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let msg_var = UInt8::new_witness_vec(ark_relations::ns!(cs, "msg"), msg)?;
+        let output_var = UInt8::new_input_vec(ark_relations::ns!(cs, "output"), &expected)?;
+
+        let digest_var = keccak256_gadget(&msg_var)?;
+        assert_eq!(digest_var.value()?, expected);
+        digest_var.enforce_equal(&output_var)?;
+        assert!(cs.is_satisfied()?);
+    }
+    println!("{} known answers checked", vectors.len());
+
+    // Native and in-circuit hashes agree around the rate (136 bytes)
+    let mut rng = ark_std::test_rng();
+    for len in [1, 32, 135, 136, 137, 271, 272, 300] {
+        let msg: Vec<u8> = (0..len).map(|_| u8::rand(&mut rng)).collect();
+
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let msg_var = UInt8::new_witness_vec(ark_relations::ns!(cs, "msg"), &msg)?;
+        let output_var = UInt8::new_input_vec(ark_relations::ns!(cs, "output"), &keccak256(&msg))?;
+        keccak256_gadget(&msg_var)?.enforce_equal(&output_var)?;
+        assert!(cs.is_satisfied()?);
+    }
+
+    // The digest of another message does not satisfy the circuit
+    let cs = ConstraintSystem::<Fr>::new_ref();
+    let msg_var = UInt8::new_witness_vec(ark_relations::ns!(cs, "msg"), b"hello")?;
+    let output_var = UInt8::new_input_vec(ark_relations::ns!(cs, "output"), &keccak256(b"hellp"))?;
+    keccak256_gadget(&msg_var)?.enforce_equal(&output_var)?;
+    assert!(!cs.is_satisfied()?);
+
+    // Constraint cost against SHA-256 on the same lengths
+    println!("{:>8} | {:>8} | {:>12} | {:>12}", "bytes", "blocks", "keccak256", "sha256");
+    for len in [0, 32, 64, 135, 136, 272, 544, 1024] {
+        let msg = vec![0xab; len];
+
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        cs.set_optimization_goal(OptimizationGoal::Constraints);
+        let msg_var = UInt8::new_witness_vec(ark_relations::ns!(cs, "msg"), &msg)?;
+        keccak256_gadget(&msg_var)?;
+        let keccak_constraints = cs.num_constraints();
+
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        cs.set_optimization_goal(OptimizationGoal::Constraints);
+        let msg_var = UInt8::new_witness_vec(ark_relations::ns!(cs, "msg"), &msg)?;
+        Sha256Gadget::evaluate(&UnitVar::default(), &msg_var)?;
+        let sha256_constraints = cs.num_constraints();
+
+        println!(
+            "{:>8} | {:>8} | {:>12} | {:>12}",
+            len,
+            keccak256_pad(&msg).len() / KECCAK256_RATE,
+            keccak_constraints,
+            sha256_constraints,
+        );
+    }
+
+    let cs = ConstraintSystem::<Fr>::new_ref();
+    cs.set_optimization_goal(OptimizationGoal::None);
+    let msg_var = UInt8::new_witness_vec(ark_relations::ns!(cs, "msg"), b"hello")?;
+    let output_var = UInt8::new_input_vec(ark_relations::ns!(cs, "output"), &keccak256(b"hello"))?;
+    keccak256_gadget(&msg_var)?.enforce_equal(&output_var)?;
+    assert!(cs.is_satisfied()?);
+
+    cs.finalize();
+
+    print_cs_details(cs);
+
+    Ok(())
+}
@@ -0,0 +1,441 @@
+use std::fmt::Debug;
+
+use ark_relations::r1cs::{ConstraintSystem, ConstraintSystemRef, SynthesisError, OptimizationGoal};
+use ark_bls12_377::Fr;
+use ark_crypto_primitives::{
+    crh::{
+        pedersen,
+        poseidon,
+        sha256::{constraints::{DigestVar, Sha256Gadget}, Sha256},
+        CRHScheme,
+        CRHSchemeGadget,
+        TwoToOneCRHScheme,
+        TwoToOneCRHSchemeGadget,
+    },
+    merkle_tree::{
+        constraints::{BytesVarDigestConverter, ConfigGadget, PathVar},
+        ByteDigestConverter,
+        Config,
+        DigestConverter,
+        IdentityDigestConverter,
+        LeafParam,
+        MerkleTree,
+        Path,
+        TwoToOneParam,
+    },
+    Error,
+};
+use ark_ed_on_bls12_377::{
+    EdwardsProjective as JubJub,
+    constraints::EdwardsVar,
+};
+use ark_ff::ToConstraintField;
+use ark_r1cs_std::{
+    alloc::AllocVar,
+    boolean::Boolean,
+    eq::EqGadget,
+    fields::fp::FpVar,
+    uint8::UInt8,
+};
+use ark_std::{rand::Rng, UniformRand};
+
+use crate::utils::poseidon::poseidon_parameters;
+
+// Merkle trees from ark_crypto_primitives::merkle_tree over BLS12-377 Fr, with the leaf and
+// two-to-one hashes taken from one of SHA-256, Pedersen (ed_on_bls12_377) or Poseidon.
+// A tree of depth d has 2^d leaves; its paths have d - 1 inner siblings plus the leaf sibling.
+
+// 4 * 256 bits: two 64-byte (uncompressed) points for the two-to-one hash
+#[derive(Clone)]
+pub struct MerkleWindow;
+impl pedersen::Window for MerkleWindow {
+    const WINDOW_SIZE: usize = 4;
+    const NUM_WINDOWS: usize = 256;
+}
+
+// SHA-256 digests are already bytes: pass them unchanged (ByteDigestConverter would add
+// the length prefix of the Vec serialization)
+pub struct Sha256DigestConverter;
+impl DigestConverter<Vec<u8>, [u8]> for Sha256DigestConverter {
+    type TargetType = Vec<u8>;
+    fn convert(item: Vec<u8>) -> Result<Vec<u8>, Error> {
+        Ok(item)
+    }
+}
+
+pub struct Sha256MerkleConfig;
+impl Config for Sha256MerkleConfig {
+    type Leaf = [u8];
+    type LeafDigest = Vec<u8>;
+    type LeafInnerDigestConverter = Sha256DigestConverter;
+    type InnerDigest = Vec<u8>;
+    type LeafHash = Sha256;
+    type TwoToOneHash = Sha256;
+}
+
+pub struct Sha256MerkleConfigVar;
+impl ConfigGadget<Sha256MerkleConfig, Fr> for Sha256MerkleConfigVar {
+    type Leaf = [UInt8<Fr>];
+    type LeafDigest = DigestVar<Fr>;
+    type LeafInnerConverter = BytesVarDigestConverter<DigestVar<Fr>, Fr>;
+    type InnerDigest = DigestVar<Fr>;
+    type LeafHash = Sha256Gadget<Fr>;
+    type TwoToOneHash = Sha256Gadget<Fr>;
+}
+
+type PedersenLeafHash = pedersen::CRH<JubJub, MerkleWindow>;
+type PedersenTwoToOneHash = pedersen::TwoToOneCRH<JubJub, MerkleWindow>;
+
+pub struct PedersenMerkleConfig;
+impl Config for PedersenMerkleConfig {
+    type Leaf = [u8];
+    type LeafDigest = <PedersenLeafHash as CRHScheme>::Output;
+    type LeafInnerDigestConverter = ByteDigestConverter<Self::LeafDigest>;
+    type InnerDigest = <PedersenTwoToOneHash as TwoToOneCRHScheme>::Output;
+    type LeafHash = PedersenLeafHash;
+    type TwoToOneHash = PedersenTwoToOneHash;
+}
+
+pub struct PedersenMerkleConfigVar;
+impl ConfigGadget<PedersenMerkleConfig, Fr> for PedersenMerkleConfigVar {
+    type Leaf = [UInt8<Fr>];
+    type LeafDigest = EdwardsVar;
+    type LeafInnerConverter = BytesVarDigestConverter<EdwardsVar, Fr>;
+    type InnerDigest = EdwardsVar;
+    type LeafHash = pedersen::constraints::CRHGadget<JubJub, EdwardsVar, MerkleWindow>;
+    type TwoToOneHash = pedersen::constraints::TwoToOneCRHGadget<JubJub, EdwardsVar, MerkleWindow>;
+}
+
+pub struct PoseidonMerkleConfig;
+impl Config for PoseidonMerkleConfig {
+    type Leaf = [Fr];
+    type LeafDigest = Fr;
+    type LeafInnerDigestConverter = IdentityDigestConverter<Fr>;
+    type InnerDigest = Fr;
+    type LeafHash = poseidon::CRH<Fr>;
+    type TwoToOneHash = poseidon::TwoToOneCRH<Fr>;
+}
+
+pub struct PoseidonMerkleConfigVar;
+impl ConfigGadget<PoseidonMerkleConfig, Fr> for PoseidonMerkleConfigVar {
+    type Leaf = [FpVar<Fr>];
+    type LeafDigest = FpVar<Fr>;
+    type LeafInnerConverter = IdentityDigestConverter<FpVar<Fr>>;
+    type InnerDigest = FpVar<Fr>;
+    type LeafHash = poseidon::constraints::CRHGadget<Fr>;
+    type TwoToOneHash = poseidon::constraints::TwoToOneCRHGadget<Fr>;
+}
+
+pub struct MerkleParameters<P: Config> {
+    pub leaf: LeafParam<P>,
+    pub two_to_one: TwoToOneParam<P>,
+}
+
+impl<P: Config> Clone for MerkleParameters<P> {
+    fn clone(&self) -> Self {
+        MerkleParameters {
+            leaf: self.leaf.clone(),
+            two_to_one: self.two_to_one.clone(),
+        }
+    }
+}
+
+// Ties a native tree configuration to its gadget, with what circuits need on top:
+// parameters, leaf encoding and the public encoding of the root
+pub trait MerkleHash {
+    type LeafElement: Clone + Debug;
+    type LeafElementVar: AllocVar<Self::LeafElement, Fr> + Clone + Debug;
+    type P: Config<Leaf = [Self::LeafElement]>;
+    type PG: ConfigGadget<Self::P, Fr, Leaf = [Self::LeafElementVar]>;
+
+    const NAME: &'static str;
+
+    fn setup<R: Rng>(rng: &mut R) -> MerkleParameters<Self::P>;
+
+    fn leaf(bytes: &[u8]) -> Vec<Self::LeafElement>;
+
+    // Allocates the root as a public input
+    fn root_input(
+        cs: ConstraintSystemRef<Fr>,
+        root: Option<&<Self::P as Config>::InnerDigest>,
+    ) -> Result<<Self::PG as ConfigGadget<Self::P, Fr>>::InnerDigest, SynthesisError>;
+
+    // Field elements produced by root_input
+    fn root_to_field_elements(root: &<Self::P as Config>::InnerDigest) -> Vec<Fr>;
+}
+
+pub struct Sha256Merkle;
+impl MerkleHash for Sha256Merkle {
+    type LeafElement = u8;
+    type LeafElementVar = UInt8<Fr>;
+    type P = Sha256MerkleConfig;
+    type PG = Sha256MerkleConfigVar;
+
+    const NAME: &'static str = "sha256";
+
+    fn setup<R: Rng>(_rng: &mut R) -> MerkleParameters<Self::P> {
+        MerkleParameters { leaf: (), two_to_one: () }
+    }
+
+    fn leaf(bytes: &[u8]) -> Vec<u8> {
+        bytes.to_vec()
+    }
+
+    // Packed as UInt8::new_input_vec does, instead of one input per bit of DigestVar
+    fn root_input(cs: ConstraintSystemRef<Fr>, root: Option<&Vec<u8>>) -> Result<DigestVar<Fr>, SynthesisError> {
+        let root = root.ok_or(SynthesisError::AssignmentMissing)?;
+        Ok(DigestVar(UInt8::new_input_vec(ark_relations::ns!(cs, "root"), root)?))
+    }
+
+    fn root_to_field_elements(root: &Vec<u8>) -> Vec<Fr> {
+        root.to_field_elements().unwrap()
+    }
+}
+
+pub struct PedersenMerkle;
+impl MerkleHash for PedersenMerkle {
+    type LeafElement = u8;
+    type LeafElementVar = UInt8<Fr>;
+    type P = PedersenMerkleConfig;
+    type PG = PedersenMerkleConfigVar;
+
+    const NAME: &'static str = "pedersen";
+
+    fn setup<R: Rng>(rng: &mut R) -> MerkleParameters<Self::P> {
+        MerkleParameters {
+            leaf: <PedersenLeafHash as CRHScheme>::setup(rng).unwrap(),
+            two_to_one: <PedersenTwoToOneHash as TwoToOneCRHScheme>::setup(rng).unwrap(),
+        }
+    }
+
+    fn leaf(bytes: &[u8]) -> Vec<u8> {
+        bytes.to_vec()
+    }
+
+    fn root_input(
+        cs: ConstraintSystemRef<Fr>,
+        root: Option<&<Self::P as Config>::InnerDigest>,
+    ) -> Result<EdwardsVar, SynthesisError> {
+        <EdwardsVar as AllocVar<<Self::P as Config>::InnerDigest, Fr>>::new_input(
+            ark_relations::ns!(cs, "root"),
+            || root.ok_or(SynthesisError::AssignmentMissing),
+        )
+    }
+
+    fn root_to_field_elements(root: &<Self::P as Config>::InnerDigest) -> Vec<Fr> {
+        vec![root.x, root.y]
+    }
+}
+
+pub struct PoseidonMerkle;
+impl MerkleHash for PoseidonMerkle {
+    type LeafElement = Fr;
+    type LeafElementVar = FpVar<Fr>;
+    type P = PoseidonMerkleConfig;
+    type PG = PoseidonMerkleConfigVar;
+
+    const NAME: &'static str = "poseidon";
+
+    fn setup<R: Rng>(_rng: &mut R) -> MerkleParameters<Self::P> {
+        let params = poseidon_parameters::<Fr>(2);
+        MerkleParameters { leaf: params.clone(), two_to_one: params }
+    }
+
+    // 31 bytes per element, as ToConstraintField for [u8]
+    fn leaf(bytes: &[u8]) -> Vec<Fr> {
+        bytes.to_field_elements().unwrap()
+    }
+
+    fn root_input(cs: ConstraintSystemRef<Fr>, root: Option<&Fr>) -> Result<FpVar<Fr>, SynthesisError> {
+        FpVar::new_input(ark_relations::ns!(cs, "root"), || root.ok_or(SynthesisError::AssignmentMissing))
+    }
+
+    fn root_to_field_elements(root: &Fr) -> Vec<Fr> {
+        vec![*root]
+    }
+}
+
+pub type LeafParamsVar<H> = <<<H as MerkleHash>::PG as ConfigGadget<<H as MerkleHash>::P, Fr>>::LeafHash
+    as CRHSchemeGadget<<<H as MerkleHash>::P as Config>::LeafHash, Fr>>::ParametersVar;
+pub type TwoToOneParamsVar<H> = <<<H as MerkleHash>::PG as ConfigGadget<<H as MerkleHash>::P, Fr>>::TwoToOneHash
+    as TwoToOneCRHSchemeGadget<<<H as MerkleHash>::P as Config>::TwoToOneHash, Fr>>::ParametersVar;
+pub type MerkleRoot<H> = <<H as MerkleHash>::P as Config>::InnerDigest;
+// Leaf, path and root, as returned by random_path
+pub type RandomPath<H> = (Vec<<H as MerkleHash>::LeafElement>, Path<<H as MerkleHash>::P>, MerkleRoot<H>);
+
+// Hash parameters are circuit constants
+pub fn merkle_parameters_var<H: MerkleHash>(
+    cs: ConstraintSystemRef<Fr>,
+    params: &MerkleParameters<H::P>,
+) -> Result<(LeafParamsVar<H>, TwoToOneParamsVar<H>), SynthesisError> {
+    let leaf_params_var = LeafParamsVar::<H>::new_constant(ark_relations::ns!(cs, "leaf_crh_parameters"), &params.leaf)?;
+    let two_to_one_params_var = TwoToOneParamsVar::<H>::new_constant(
+        ark_relations::ns!(cs, "two_to_one_crh_parameters"),
+        &params.two_to_one,
+    )?;
+    Ok((leaf_params_var, two_to_one_params_var))
+}
+
+// Enforces that `leaf_var` sits at the position given by `path_var` under `root_var`
+pub fn enforce_membership<H: MerkleHash>(
+    params_var: &(LeafParamsVar<H>, TwoToOneParamsVar<H>),
+    root_var: &<H::PG as ConfigGadget<H::P, Fr>>::InnerDigest,
+    leaf_var: &[H::LeafElementVar],
+    path_var: &PathVar<H::P, Fr, H::PG>,
+) -> Result<(), SynthesisError> {
+    path_var
+        .verify_membership(&params_var.0, &params_var.1, root_var, leaf_var)?
+        .enforce_equal(&Boolean::TRUE)
+}
+
+// Root above `leaf` along `path`, as in Path::verify
+pub fn path_root<P: Config>(
+    params: &MerkleParameters<P>,
+    path: &Path<P>,
+    leaf: &P::Leaf,
+) -> Result<P::InnerDigest, Error> {
+    let leaf_hash = P::LeafHash::evaluate(&params.leaf, leaf)?;
+    let (left, right) = if path.leaf_index & 1 == 0 {
+        (leaf_hash, path.leaf_sibling_hash.clone())
+    } else {
+        (path.leaf_sibling_hash.clone(), leaf_hash)
+    };
+    let mut node = P::TwoToOneHash::evaluate(
+        &params.two_to_one,
+        P::LeafInnerDigestConverter::convert(left)?,
+        P::LeafInnerDigestConverter::convert(right)?,
+    )?;
+
+    let mut index = path.leaf_index >> 1;
+    for sibling in path.auth_path.iter().rev() {
+        node = if index & 1 == 0 {
+            P::TwoToOneHash::compress(&params.two_to_one, &node, sibling)?
+        } else {
+            P::TwoToOneHash::compress(&params.two_to_one, sibling, &node)?
+        };
+        index >>= 1;
+    }
+    Ok(node)
+}
+
+// A path of any depth with random siblings, for trees too large to build (depth 32).
+// Returns the leaf, the path and the matching root.
+pub fn random_path<H: MerkleHash, R: Rng>(
+    params: &MerkleParameters<H::P>,
+    depth: usize,
+    rng: &mut R,
+) -> RandomPath<H> {
+    let mut random_leaf = || {
+        let bytes: Vec<u8> = (0..32).map(|_| u8::rand(rng)).collect();
+        H::leaf(&bytes)
+    };
+    let mut leaf_digest = || <H::P as Config>::LeafHash::evaluate(&params.leaf, random_leaf().as_slice()).unwrap();
+
+    let leaf_sibling_hash = leaf_digest();
+    let auth_path = (0..depth - 1).map(|_| {
+        <H::P as Config>::TwoToOneHash::evaluate(
+            &params.two_to_one,
+            <H::P as Config>::LeafInnerDigestConverter::convert(leaf_digest()).unwrap(),
+            <H::P as Config>::LeafInnerDigestConverter::convert(leaf_digest()).unwrap(),
+        ).unwrap()
+    }).collect();
+
+    let leaf = random_leaf();
+    let path = Path {
+        leaf_sibling_hash,
+        auth_path,
+        leaf_index: (rng.next_u64() as usize) & ((1 << depth) - 1),
+    };
+    let root = path_root(params, &path, leaf.as_slice()).unwrap();
+    (leaf, path, root)
+}
+
+// Builds a tree of 2^depth leaves, checks every tested path natively and in-circuit, and that
+// a wrong leaf, position or root is rejected
+fn check_merkle_tree<H: MerkleHash>(depth: usize) -> Result<(), SynthesisError> {
+    let mut rng = ark_std::test_rng();
+    let params = H::setup(&mut rng);
+
+    let leaves: Vec<Vec<H::LeafElement>> = (0..1u32 << depth)
+        .map(|i| H::leaf(format!("leaf {}", i).as_bytes()))
+        .collect();
+    let tree = MerkleTree::<H::P>::new(&params.leaf, &params.two_to_one, leaves.iter().map(|leaf| leaf.as_slice())).unwrap();
+    let root = tree.root();
+
+    for index in [0, 1, 2, (1 << depth) - 1] {
+        let path = tree.generate_proof(index).unwrap();
+        assert!(path.verify(&params.leaf, &params.two_to_one, &root, leaves[index].as_slice()).unwrap());
+        assert!(path_root(&params, &path, leaves[index].as_slice()).unwrap() == root);
+
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let params_var = merkle_parameters_var::<H>(cs.clone(), &params)?;
+        let root_var = H::root_input(cs.clone(), Some(&root))?;
+        let leaf_var = Vec::<H::LeafElementVar>::new_witness(ark_relations::ns!(cs, "leaf"), || Ok(leaves[index].as_slice()))?;
+        let path_var = PathVar::<H::P, Fr, H::PG>::new_witness(ark_relations::ns!(cs, "path"), || Ok(&path))?;
+        enforce_membership::<H>(&params_var, &root_var, &leaf_var, &path_var)?;
+        assert!(cs.is_satisfied()?);
+    }
+
+    let path = tree.generate_proof(2).unwrap();
+    let mut wrong_index = path.clone();
+    wrong_index.leaf_index = 3;
+    let other_root = tree.generate_proof(1).unwrap().auth_path[0].clone();
+    let wrong_cases = [
+        (leaves[3].clone(), path.clone(), root.clone()),
+        (leaves[2].clone(), wrong_index, root.clone()),
+        (leaves[2].clone(), path.clone(), other_root),
+    ];
+    for (leaf, path, root) in wrong_cases {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let params_var = merkle_parameters_var::<H>(cs.clone(), &params)?;
+        let root_var = H::root_input(cs.clone(), Some(&root))?;
+        let leaf_var = Vec::<H::LeafElementVar>::new_witness(ark_relations::ns!(cs, "leaf"), || Ok(leaf.as_slice()))?;
+        let path_var = PathVar::<H::P, Fr, H::PG>::new_witness(ark_relations::ns!(cs, "path"), || Ok(&path))?;
+        enforce_membership::<H>(&params_var, &root_var, &leaf_var, &path_var)?;
+        assert!(!cs.is_satisfied()?);
+    }
+
+    println!("{}: depth {} tree checked", H::NAME, depth);
+    Ok(())
+}
+
+// Constraints and witness variables of one membership check
+fn merkle_cost<H: MerkleHash>(depth: usize) -> Result<(usize, usize), SynthesisError> {
+    let mut rng = ark_std::test_rng();
+    let params = H::setup(&mut rng);
+    let (leaf, path, root) = random_path::<H, _>(&params, depth, &mut rng);
+
+    let cs = ConstraintSystem::<Fr>::new_ref();
+    cs.set_optimization_goal(OptimizationGoal::Constraints);
+    let params_var = merkle_parameters_var::<H>(cs.clone(), &params)?;
+    let root_var = H::root_input(cs.clone(), Some(&root))?;
+    let leaf_var = Vec::<H::LeafElementVar>::new_witness(ark_relations::ns!(cs, "leaf"), || Ok(leaf.as_slice()))?;
+    let path_var = PathVar::<H::P, Fr, H::PG>::new_witness(ark_relations::ns!(cs, "path"), || Ok(&path))?;
+    enforce_membership::<H>(&params_var, &root_var, &leaf_var, &path_var)?;
+    assert!(cs.is_satisfied()?);
+
+    cs.finalize();
+
+    Ok((cs.num_constraints(), cs.num_witness_variables()))
+}
+
+pub fn test_merkle_tree() -> Result<(), SynthesisError> {
+    println!("\n### Running test_merkle_tree()...");
+
+    check_merkle_tree::<Sha256Merkle>(4)?;
+    check_merkle_tree::<PedersenMerkle>(4)?;
+    check_merkle_tree::<PoseidonMerkle>(4)?;
+
+    println!("{:>6} | {:>10} | {:>12} | {:>12}", "depth", "hash", "constraints", "witness");
+    for depth in [8, 16, 20, 32] {
+        for (name, (constraints, witness)) in [
+            (Sha256Merkle::NAME, merkle_cost::<Sha256Merkle>(depth)?),
+            (PedersenMerkle::NAME, merkle_cost::<PedersenMerkle>(depth)?),
+            (PoseidonMerkle::NAME, merkle_cost::<PoseidonMerkle>(depth)?),
+        ] {
+            println!("{:>6} | {:>10} | {:>12} | {:>12}", depth, name, constraints, witness);
+        }
+    }
+
+    Ok(())
+}
@@ -4,10 +4,29 @@ mod pairing;
 mod emulation;
 mod group_var;
 mod crh;
+mod hash_to_curve;
+mod keccak;
+mod commitment;
+mod merkle;
+mod sparse_merkle;
+mod schnorr;
+mod range;
+mod eddsa;
 
 pub use uint8::test_uint8;
 pub use fq::test_fq_var;
-pub use pairing::test_pairing;
+pub use pairing::{test_pairing, test_pairing_costs};
 pub use emulation::{test_add, test_mul, test_mul_without_reduce};
 pub use group_var::test_GVar;
-pub use crh::{test_sha256_crh, test_pedersen_crh};
\ No newline at end of file
+pub use crh::{test_sha256_crh, test_sha256_vectors, test_blake2s_crh, test_pedersen_crh, test_pedersen_window_sweep, test_bowe_hopwood_crh, test_poseidon_crh, test_crh_costs, test_mimc_crh};
+pub use crh::{HashFunction, HashParameters, blake2s_gadget, blake2s_prf_gadget, mimc_hash_gadget, mimc_feistel_hash_gadget};
+pub use crh::{PoseidonCRH, PoseidonCRHGadget, PoseidonCRHParametersVar};
+pub use hash_to_curve::test_hash_to_curve;
+pub use keccak::test_keccak256;
+pub use commitment::{test_pedersen_commitment, PedersenCommitment, PedersenCommitmentGadget};
+pub use merkle::{test_merkle_tree, MerkleHash, MerkleParameters, Sha256Merkle, PedersenMerkle, PoseidonMerkle, merkle_parameters_var, enforce_membership, random_path, MerkleRoot};
+pub use sparse_merkle::{test_sparse_merkle_tree, smt_membership_gadget, smt_non_membership_gadget};
+pub use schnorr::{test_schnorr_signature, test_blake2s_schnorr_signature, SchnorrSignatureVar, schnorr_verify_gadget};
+pub use schnorr::{Blake2sSchnorr, Blake2sSchnorrParametersVar, Blake2sSchnorrSignatureVar, Blake2sSchnorrVerifyGadget};
+pub use range::{test_range_check, enforce_range_gadget};
+pub use eddsa::{test_eddsa_signature, EddsaHashVar, EddsaSignatureVar, eddsa_verify_gadget};
\ No newline at end of file
@@ -9,7 +9,7 @@ use ark_bls12_377::{ // NOTE: The crate ark_bls12_381 does not support feature "
 };
 use ark_std::{UniformRand}; // To call Fq::rand()
 use ark_r1cs_std::{
-    alloc::AllocVar, // Needed to call new_witness
+    alloc::{AllocVar, AllocationMode}, // Needed to call new_witness
     pairing::PairingVar, // To call prepare_g()
     eq::EqGadget, // To call enforce_equal()
     R1CSVar, // To call value()
@@ -55,5 +55,63 @@ pub fn test_pairing() -> Result<(), SynthesisError> {
 
     print_cs_details(cs);
 
+    Ok(())
+}
+
+// Constraint cost of every step of PairingVar::pairing, for each allocation mode of the G1 and G2 inputs
+pub fn test_pairing_costs() -> Result<(), SynthesisError> {
+    println!("\n### Running test_pairing_costs()...");
+
+    type Bls12PairingVar = ark_bls12_377::constraints::PairingVar;
+
+    let mut rng = ark_std::test_rng();
+
+    let a_native = G1Projective::rand(&mut rng);
+    let b_native = G2Projective::rand(&mut rng);
+    let pairing_result_native = Bls12_377::pairing(a_native, b_native);
+
+    let modes = [
+        (AllocationMode::Witness, AllocationMode::Witness),
+        (AllocationMode::Witness, AllocationMode::Constant),
+        (AllocationMode::Constant, AllocationMode::Witness),
+        (AllocationMode::Constant, AllocationMode::Constant),
+    ];
+
+    println!(
+        "{:>10} | {:>10} | {:>10} | {:>10} | {:>11} | {:>10} | {:>10}",
+        "G1", "G2", "prepare_g1", "prepare_g2", "miller_loop", "final_exp", "total",
+    );
+    for (g1_mode, g2_mode) in modes {
+        let cs = ConstraintSystem::<Fq>::new_ref();
+        cs.set_optimization_goal(OptimizationGoal::None);
+
+        let a = G1Var::new_variable(ark_relations::ns!(cs, "a"), || Ok(a_native), g1_mode)?;
+        let b = G2Var::new_variable(ark_relations::ns!(cs, "b"), || Ok(b_native), g2_mode)?;
+        let after_alloc = cs.num_constraints();
+
+        let a_prep = Bls12PairingVar::prepare_g1(&a)?;
+        let after_g1 = cs.num_constraints();
+        let b_prep = Bls12PairingVar::prepare_g2(&b)?;
+        let after_g2 = cs.num_constraints();
+        let miller_result = Bls12PairingVar::miller_loop(&[a_prep], &[b_prep])?;
+        let after_miller = cs.num_constraints();
+        let pairing_result = Bls12PairingVar::final_exponentiation(&miller_result)?;
+        let after_final_exp = cs.num_constraints();
+
+        assert_eq!(pairing_result.value()?, pairing_result_native.0);
+        assert!(cs.is_satisfied()?);
+
+        println!(
+            "{:>10} | {:>10} | {:>10} | {:>10} | {:>11} | {:>10} | {:>10}",
+            format!("{:?}", g1_mode),
+            format!("{:?}", g2_mode),
+            after_g1 - after_alloc,
+            after_g2 - after_g1,
+            after_miller - after_g2,
+            after_final_exp - after_miller,
+            after_final_exp - after_alloc,
+        );
+    }
+
     Ok(())
 }
\ No newline at end of file
@@ -0,0 +1,45 @@
+use ark_relations::r1cs::{ConstraintSystem, SynthesisError};
+use ark_bls12_377::Fr;
+use ark_ff::{BigInteger, PrimeField};
+use ark_r1cs_std::{
+    alloc::AllocVar,
+    boolean::Boolean,
+    eq::EqGadget,
+    fields::fp::FpVar,
+    R1CSVar,
+};
+
+// Enforces 0 <= value < 2^num_bits by decomposing it into num_bits witness bits
+// (num_bits + 1 constraints). Values that do not fit leave the system unsatisfied.
+pub fn enforce_range_gadget<F: PrimeField>(value: &FpVar<F>, num_bits: usize) -> Result<(), SynthesisError> {
+    assert!(num_bits < F::MODULUS_BIT_SIZE as usize);
+
+    // One allocation per bit, so that setup (where values are missing) knows the layout
+    let cs = value.cs();
+    let bits = value.value().map(|value| value.into_bigint().to_bits_le());
+    let bits_var = (0..num_bits)
+        .map(|i| Boolean::new_witness(ark_relations::ns!(cs, "bit"), || bits.as_ref().map(|bits| bits[i]).map_err(|e| *e)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Boolean::le_bits_to_fp_var(&bits_var)?.enforce_equal(value)
+}
+
+pub fn test_range_check() -> Result<(), SynthesisError> {
+    println!("\n### Running test_range_check()...");
+
+    let cases = [
+        (Fr::from(0u64), true),
+        (Fr::from(u64::MAX), true),
+        (Fr::from(u64::MAX) + Fr::from(1u64), false),
+        (-Fr::from(1u64), false),
+    ];
+    for (value, in_range) in cases {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let value_var = FpVar::new_witness(ark_relations::ns!(cs, "value"), || Ok(value))?;
+        enforce_range_gadget(&value_var, 64)?;
+        assert_eq!(cs.is_satisfied()?, in_range);
+        assert_eq!(cs.num_constraints(), 65);
+    }
+
+    Ok(())
+}
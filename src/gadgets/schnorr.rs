@@ -0,0 +1,360 @@
+use std::borrow::Borrow;
+
+use ark_relations::r1cs::{ConstraintSystem, Namespace, SynthesisError, OptimizationGoal};
+use ark_bls12_377::Fr;
+use ark_crypto_primitives::{
+    crh::CRHSchemeGadget,
+    signature::{constraints::SigVerifyGadget, schnorr, SignatureScheme},
+};
+use ark_ec::{AffineRepr, Group};
+use ark_ed_on_bls12_377::{
+    EdwardsAffine,
+    EdwardsProjective,
+    Fr as ScalarF,
+    constraints::EdwardsVar,
+};
+use ark_ff::{BigInteger, PrimeField};
+use ark_serialize::CanonicalSerialize;
+use ark_r1cs_std::{
+    alloc::{AllocVar, AllocationMode},
+    boolean::Boolean,
+    eq::EqGadget,
+    fields::{fp::FpVar, FieldVar},
+    groups::CurveVar,
+    uint8::UInt8,
+    R1CSVar,
+    ToBitsGadget,
+    ToBytesGadget,
+};
+use ark_std::UniformRand;
+use blake2::Blake2s256;
+
+use crate::gadgets::{blake2s_gadget, PoseidonCRHGadget, PoseidonCRHParametersVar};
+use crate::utils::schnorr::{schnorr_parameters, schnorr_verify, SchnorrKeyPair, SchnorrSignature};
+
+// In-circuit verification of the Poseidon-challenge Schnorr signatures of utils::schnorr.
+// ed_on_bls12_377 points are EdwardsVar over BLS12-377 Fr, so there is no field emulation.
+
+// s as its bits (little endian), e as a field element
+#[derive(Clone)]
+pub struct SchnorrSignatureVar {
+    pub s: Vec<Boolean<Fr>>,
+    pub e: FpVar<Fr>,
+}
+
+impl AllocVar<SchnorrSignature, Fr> for SchnorrSignatureVar {
+    fn new_variable<T: Borrow<SchnorrSignature>>(
+        cs: impl Into<Namespace<Fr>>,
+        f: impl FnOnce() -> Result<T, SynthesisError>,
+        mode: AllocationMode,
+    ) -> Result<Self, SynthesisError> {
+        let ns = cs.into();
+        let cs = ns.cs();
+        let signature = f().map(|signature| signature.borrow().clone());
+
+        let s_bits = signature.as_ref().map(|signature| {
+            let mut bits = signature.s.into_bigint().to_bits_le();
+            bits.truncate(ScalarF::MODULUS_BIT_SIZE as usize);
+            bits
+        }).map_err(|e| *e);
+        let s = Vec::<Boolean<Fr>>::new_variable(ark_relations::ns!(cs, "s"), || s_bits, mode)?;
+        let e = FpVar::new_variable(ark_relations::ns!(cs, "e"), || signature.map(|signature| signature.e), mode)?;
+
+        Ok(SchnorrSignatureVar { s, e })
+    }
+}
+
+// bits * base (bits little endian); for a constant base, its powers of two are precomputed
+pub fn fixed_base_mul_gadget(base: &EdwardsVar, bits: &[Boolean<Fr>]) -> Result<EdwardsVar, SynthesisError> {
+    if !base.is_constant() {
+        return base.scalar_mul_le(bits.iter());
+    }
+    let mut power = base.value()?;
+    let powers: Vec<EdwardsProjective> = bits
+        .iter()
+        .map(|_| {
+            let current = power;
+            power.double_in_place();
+            current
+        })
+        .collect();
+    let mut result = EdwardsVar::zero();
+    result.precomputed_base_scalar_mul_le(bits.iter().zip(&powers))?;
+    Ok(result)
+}
+
+// Returns whether H(s G + e pk, pk, msg) == e
+pub fn schnorr_verify_gadget(
+    params: &PoseidonCRHParametersVar,
+    pk: &EdwardsVar,
+    msg: &[FpVar<Fr>],
+    signature: &SchnorrSignatureVar,
+) -> Result<Boolean<Fr>, SynthesisError> {
+    let generator = EdwardsVar::constant(EdwardsAffine::generator().into_group());
+    let s_g = fixed_base_mul_gadget(&generator, &signature.s)?;
+    let e_pk = pk.scalar_mul_le(signature.e.to_bits_le()?.iter())?;
+    let r = s_g + e_pk;
+
+    let mut input = vec![r.x, r.y, pk.x.clone(), pk.y.clone()];
+    input.extend_from_slice(msg);
+    let e = PoseidonCRHGadget::evaluate(params, &input)?;
+
+    e.is_eq(&signature.e)
+}
+
+// Returns (verified in-circuit, constraints)
+fn run_schnorr(pk: &EdwardsAffine, msg: &[Fr], signature: &SchnorrSignature) -> Result<(bool, usize), SynthesisError> {
+    let cs = ConstraintSystem::<Fr>::new_ref();
+    cs.set_optimization_goal(OptimizationGoal::Constraints);
+
+    let params_var = PoseidonCRHParametersVar::new_constant(ark_relations::ns!(cs, "CRH Parameters"), schnorr_parameters())?;
+    let pk_var = EdwardsVar::new_input(ark_relations::ns!(cs, "pk"), || Ok(*pk))?;
+    let msg_var = Vec::<FpVar<Fr>>::new_input(ark_relations::ns!(cs, "msg"), || Ok(msg))?;
+    let signature_var = SchnorrSignatureVar::new_witness(ark_relations::ns!(cs, "signature"), || Ok(signature))?;
+
+    let verified = schnorr_verify_gadget(&params_var, &pk_var, &msg_var, &signature_var)?;
+    assert!(cs.is_satisfied()?);
+
+    cs.finalize();
+
+    Ok((verified.value()?, cs.num_constraints()))
+}
+
+pub fn test_schnorr_signature() -> Result<(), SynthesisError> {
+    println!("\n### Running test_schnorr_signature()...");
+
+    let mut rng = ark_std::test_rng();
+    let params = schnorr_parameters();
+
+    let key_pair = SchnorrKeyPair::generate(&mut rng);
+    let other_key_pair = SchnorrKeyPair::generate(&mut rng);
+    let msg: Vec<Fr> = (0..4).map(|_| Fr::rand(&mut rng)).collect();
+    let signature = key_pair.sign(&params, &msg, &mut rng);
+    assert!(schnorr_verify(&params, &key_pair.pk, &msg, &signature));
+
+    let (verified, constraints) = run_schnorr(&key_pair.pk, &msg, &signature)?;
+    assert!(verified);
+    println!("verification of a {}-element message: {} constraints", msg.len(), constraints);
+
+    let mut other_msg = msg.clone();
+    other_msg[3] += Fr::from(1u64);
+    let forged_s = SchnorrSignature { s: signature.s + ScalarF::from(1u64), ..signature.clone() };
+    let forged_e = SchnorrSignature { e: signature.e + Fr::from(1u64), ..signature.clone() };
+    let forgeries = [
+        (key_pair.pk, other_msg.clone(), signature.clone()),
+        (other_key_pair.pk, msg.clone(), signature.clone()),
+        (key_pair.pk, msg.clone(), forged_s),
+        (key_pair.pk, msg.clone(), forged_e),
+        (key_pair.pk, msg.clone(), other_key_pair.sign(&params, &msg, &mut rng)),
+    ];
+    for (pk, msg, signature) in forgeries {
+        assert!(!schnorr_verify(&params, &pk, &msg, &signature));
+        assert!(!run_schnorr(&pk, &msg, &signature)?.0);
+    }
+
+    Ok(())
+}
+
+
+// Verification of the Schnorr signatures of ark_crypto_primitives::signature::schnorr on
+// ed_on_bls12_377 with BLAKE2s, which only provides a public-key randomization gadget:
+//  verify: e == H(salt || compress(s G + e pk) || msg), e being the low 251 bits of the digest
+// where msg is serialized with its length (u64, little endian) first.
+
+pub type Blake2sSchnorr = schnorr::Schnorr<EdwardsProjective, Blake2s256>;
+
+const SCALAR_BITS: usize = ScalarF::MODULUS_BIT_SIZE as usize;
+
+#[derive(Clone)]
+pub struct Blake2sSchnorrParametersVar {
+    pub generator: EdwardsVar,
+    pub salt: Vec<UInt8<Fr>>,
+}
+
+impl AllocVar<<Blake2sSchnorr as SignatureScheme>::Parameters, Fr> for Blake2sSchnorrParametersVar {
+    fn new_variable<T: Borrow<<Blake2sSchnorr as SignatureScheme>::Parameters>>(
+        cs: impl Into<Namespace<Fr>>,
+        f: impl FnOnce() -> Result<T, SynthesisError>,
+        mode: AllocationMode,
+    ) -> Result<Self, SynthesisError> {
+        let ns = cs.into();
+        let cs = ns.cs();
+        let params = f()?;
+        let params = params.borrow();
+
+        let generator = EdwardsVar::new_variable(ark_relations::ns!(cs, "generator"), || Ok(params.generator), mode)?;
+        let salt = Vec::<UInt8<Fr>>::new_variable(ark_relations::ns!(cs, "salt"), || Ok(params.salt.as_slice()), mode)?;
+
+        Ok(Blake2sSchnorrParametersVar { generator, salt })
+    }
+}
+
+// Both scalars as their bits (little endian)
+#[derive(Clone)]
+pub struct Blake2sSchnorrSignatureVar {
+    pub prover_response: Vec<Boolean<Fr>>,
+    pub verifier_challenge: Vec<Boolean<Fr>>,
+}
+
+fn scalar_bits(scalar: &ScalarF) -> Vec<bool> {
+    let mut bits = scalar.into_bigint().to_bits_le();
+    bits.truncate(SCALAR_BITS);
+    bits
+}
+
+impl AllocVar<<Blake2sSchnorr as SignatureScheme>::Signature, Fr> for Blake2sSchnorrSignatureVar {
+    fn new_variable<T: Borrow<<Blake2sSchnorr as SignatureScheme>::Signature>>(
+        cs: impl Into<Namespace<Fr>>,
+        f: impl FnOnce() -> Result<T, SynthesisError>,
+        mode: AllocationMode,
+    ) -> Result<Self, SynthesisError> {
+        let ns = cs.into();
+        let cs = ns.cs();
+        let signature = f().map(|signature| {
+            let signature = signature.borrow();
+            (scalar_bits(&signature.prover_response), scalar_bits(&signature.verifier_challenge))
+        });
+
+        let prover_response = Vec::<Boolean<Fr>>::new_variable(
+            ark_relations::ns!(cs, "prover_response"),
+            || signature.as_ref().map(|signature| signature.0.as_slice()).map_err(|e| *e),
+            mode,
+        )?;
+        let verifier_challenge = Vec::<Boolean<Fr>>::new_variable(
+            ark_relations::ns!(cs, "verifier_challenge"),
+            || signature.as_ref().map(|signature| signature.1.as_slice()).map_err(|e| *e),
+            mode,
+        )?;
+
+        Ok(Blake2sSchnorrSignatureVar { prover_response, verifier_challenge })
+    }
+}
+
+fn bits_to_bytes(bits: &[Boolean<Fr>]) -> Vec<UInt8<Fr>> {
+    bits.chunks(8)
+        .map(|chunk| {
+            let mut chunk = chunk.to_vec();
+            chunk.resize(8, Boolean::FALSE);
+            UInt8::from_bits_le(&chunk)
+        })
+        .collect()
+}
+
+impl ToBytesGadget<Fr> for Blake2sSchnorrSignatureVar {
+    fn to_bytes(&self) -> Result<Vec<UInt8<Fr>>, SynthesisError> {
+        let mut bytes = bits_to_bytes(&self.prover_response);
+        bytes.extend(bits_to_bytes(&self.verifier_challenge));
+        Ok(bytes)
+    }
+}
+
+// serialize_compressed of a twisted Edwards point: y, with bit 255 set when x > -x,
+// i.e. when 2x mod p is odd
+pub fn compressed_point_gadget(point: &EdwardsVar) -> Result<Vec<UInt8<Fr>>, SynthesisError> {
+    let mut bits = point.y.to_bits_le()?;
+    bits.resize(256, Boolean::FALSE);
+    bits[255] = point.x.double()?.to_bits_le()?[0].clone();
+    Ok(bits_to_bytes(&bits))
+}
+
+pub struct Blake2sSchnorrVerifyGadget;
+
+impl SigVerifyGadget<Blake2sSchnorr, Fr> for Blake2sSchnorrVerifyGadget {
+    type ParametersVar = Blake2sSchnorrParametersVar;
+    type PublicKeyVar = EdwardsVar;
+    type SignatureVar = Blake2sSchnorrSignatureVar;
+
+    fn verify(
+        parameters: &Self::ParametersVar,
+        public_key: &Self::PublicKeyVar,
+        message: &[UInt8<Fr>],
+        signature: &Self::SignatureVar,
+    ) -> Result<Boolean<Fr>, SynthesisError> {
+        let s_g = fixed_base_mul_gadget(&parameters.generator, &signature.prover_response)?;
+        let e_pk = public_key.scalar_mul_le(signature.verifier_challenge.iter())?;
+        let commitment = s_g + e_pk;
+
+        let mut hash_input = parameters.salt.clone();
+        hash_input.extend(compressed_point_gadget(&commitment)?);
+        hash_input.extend(UInt8::constant_vec(&(message.len() as u64).to_le_bytes()));
+        hash_input.extend_from_slice(message);
+        let digest_bits = blake2s_gadget(&hash_input)?.to_bits_le()?;
+
+        digest_bits[..SCALAR_BITS].is_eq(&signature.verifier_challenge)
+    }
+}
+
+// Returns (verified in-circuit, constraints)
+fn run_blake2s_schnorr(
+    params: &<Blake2sSchnorr as SignatureScheme>::Parameters,
+    pk: &EdwardsAffine,
+    msg: &[u8],
+    signature: &<Blake2sSchnorr as SignatureScheme>::Signature,
+) -> Result<(bool, usize), SynthesisError> {
+    let cs = ConstraintSystem::<Fr>::new_ref();
+    cs.set_optimization_goal(OptimizationGoal::Constraints);
+
+    let params_var = Blake2sSchnorrParametersVar::new_constant(ark_relations::ns!(cs, "parameters"), params)?;
+    let pk_var = EdwardsVar::new_input(ark_relations::ns!(cs, "pk"), || Ok(*pk))?;
+    let msg_var = UInt8::new_input_vec(ark_relations::ns!(cs, "msg"), msg)?;
+    let signature_var = Blake2sSchnorrSignatureVar::new_witness(ark_relations::ns!(cs, "signature"), || Ok(signature))?;
+
+    let verified = Blake2sSchnorrVerifyGadget::verify(&params_var, &pk_var, &msg_var, &signature_var)?;
+    assert!(cs.is_satisfied()?);
+
+    cs.finalize();
+
+    Ok((verified.value()?, cs.num_constraints()))
+}
+
+pub fn test_blake2s_schnorr_signature() -> Result<(), SynthesisError> {
+    println!("\n### Running test_blake2s_schnorr_signature()...");
+
+    let mut rng = ark_std::test_rng();
+
+    // Point compression against serialize_compressed, for both signs of x
+    let cs = ConstraintSystem::<Fr>::new_ref();
+    for point in [EdwardsAffine::generator(), -EdwardsAffine::generator(), EdwardsAffine::zero()] {
+        let mut expected = Vec::new();
+        point.serialize_compressed(&mut expected).unwrap();
+        let point_var = EdwardsVar::new_witness(ark_relations::ns!(cs, "point"), || Ok(point))?;
+        assert_eq!(compressed_point_gadget(&point_var)?.value()?, expected);
+    }
+
+    let params = Blake2sSchnorr::setup(&mut rng).unwrap();
+    let (pk, sk) = Blake2sSchnorr::keygen(&params, &mut rng).unwrap();
+    let (other_pk, other_sk) = Blake2sSchnorr::keygen(&params, &mut rng).unwrap();
+
+    println!("{:>10} | {:>12}", "msg bytes", "constraints");
+    for len in [0, 32, 64, 128] {
+        let msg: Vec<u8> = (0..len).map(|_| u8::rand(&mut rng)).collect();
+        let signature = Blake2sSchnorr::sign(&params, &sk, &msg, &mut rng).unwrap();
+        assert!(Blake2sSchnorr::verify(&params, &pk, &msg, &signature).unwrap());
+
+        let (verified, constraints) = run_blake2s_schnorr(&params, &pk, &msg, &signature)?;
+        assert!(verified);
+        println!("{:>10} | {:>12}", len, constraints);
+    }
+
+    let msg: Vec<u8> = (0..32).map(|_| u8::rand(&mut rng)).collect();
+    let signature = Blake2sSchnorr::sign(&params, &sk, &msg, &mut rng).unwrap();
+    let mut other_msg = msg.clone();
+    other_msg[0] ^= 1;
+    let mut forged_response = signature.clone();
+    forged_response.prover_response += ScalarF::from(1u64);
+    let mut forged_challenge = signature.clone();
+    forged_challenge.verifier_challenge += ScalarF::from(1u64);
+    let forgeries = [
+        (pk, other_msg, signature.clone()),
+        (other_pk, msg.clone(), signature.clone()),
+        (pk, msg.clone(), forged_response),
+        (pk, msg.clone(), forged_challenge),
+        (pk, msg.clone(), Blake2sSchnorr::sign(&params, &other_sk, &msg, &mut rng).unwrap()),
+    ];
+    for (pk, msg, signature) in forgeries {
+        assert!(!Blake2sSchnorr::verify(&params, &pk, &msg, &signature).unwrap());
+        assert!(!run_blake2s_schnorr(&params, &pk, &msg, &signature)?.0);
+    }
+
+    Ok(())
+}
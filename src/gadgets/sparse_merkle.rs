@@ -0,0 +1,176 @@
+use ark_relations::r1cs::{ConstraintSystem, SynthesisError, OptimizationGoal};
+use ark_bls12_377::Fr;
+use ark_crypto_primitives::crh::CRHSchemeGadget;
+use ark_ff::Zero;
+use ark_r1cs_std::{
+    alloc::AllocVar,
+    eq::EqGadget,
+    fields::{fp::FpVar, FieldVar},
+    select::CondSelectGadget,
+    uint8::UInt8,
+    ToBitsGadget,
+    ToConstraintFieldGadget,
+};
+
+use crate::gadgets::{PoseidonCRHGadget, PoseidonCRHParametersVar};
+use crate::utils::sparse_merkle::{leaf_hash, random_key, smt_parameters, SparseMerkleKey, SparseMerkleTree, SMT_DEPTH};
+
+// In-circuit counterpart of utils::sparse_merkle (same leaf and node hashes, same key bit order)
+
+pub fn smt_leaf_gadget(
+    params: &PoseidonCRHParametersVar,
+    key: &[UInt8<Fr>],
+    value: &FpVar<Fr>,
+) -> Result<FpVar<Fr>, SynthesisError> {
+    let mut input = key.to_constraint_field()?;
+    input.push(value.clone());
+    PoseidonCRHGadget::evaluate(params, &input)
+}
+
+// Root above `leaf` at the position of `key`, with `siblings` from the leaf up
+pub fn smt_root_gadget(
+    params: &PoseidonCRHParametersVar,
+    key: &[UInt8<Fr>],
+    leaf: FpVar<Fr>,
+    siblings: &[FpVar<Fr>],
+) -> Result<FpVar<Fr>, SynthesisError> {
+    assert_eq!(siblings.len(), SMT_DEPTH);
+
+    let bits = key.to_bits_le()?;
+    let mut node = leaf;
+    for (bit, sibling) in bits.iter().zip(siblings) {
+        let left = FpVar::conditionally_select(bit, sibling, &node)?;
+        let right = FpVar::conditionally_select(bit, &node, sibling)?;
+        node = PoseidonCRHGadget::evaluate(params, &[left, right])?;
+    }
+    Ok(node)
+}
+
+// Enforces that `key` maps to `value` under `root`
+pub fn smt_membership_gadget(
+    params: &PoseidonCRHParametersVar,
+    root: &FpVar<Fr>,
+    key: &[UInt8<Fr>],
+    value: &FpVar<Fr>,
+    siblings: &[FpVar<Fr>],
+) -> Result<(), SynthesisError> {
+    let leaf = smt_leaf_gadget(params, key, value)?;
+    smt_root_gadget(params, key, leaf, siblings)?.enforce_equal(root)
+}
+
+// Enforces that `key` is absent under `root`: its leaf is the empty leaf
+pub fn smt_non_membership_gadget(
+    params: &PoseidonCRHParametersVar,
+    root: &FpVar<Fr>,
+    key: &[UInt8<Fr>],
+    siblings: &[FpVar<Fr>],
+) -> Result<(), SynthesisError> {
+    let leaf = FpVar::zero();
+    smt_root_gadget(params, key, leaf, siblings)?.enforce_equal(root)
+}
+
+// Builds the membership (value = Some) or non-membership (value = None) check of `key` under
+// `root` and returns (satisfied, constraints)
+fn check_smt(
+    tree: &SparseMerkleTree,
+    root: Fr,
+    key: &SparseMerkleKey,
+    value: Option<Fr>,
+) -> Result<(bool, usize), SynthesisError> {
+    let path = tree.prove(key);
+
+    let cs = ConstraintSystem::<Fr>::new_ref();
+    cs.set_optimization_goal(OptimizationGoal::Constraints);
+    let params_var = PoseidonCRHParametersVar::new_constant(ark_relations::ns!(cs, "CRH Parameters"), &tree.params)?;
+    let root_var = FpVar::new_input(ark_relations::ns!(cs, "root"), || Ok(root))?;
+    let key_var = UInt8::new_input_vec(ark_relations::ns!(cs, "key"), key)?;
+    let siblings_var = Vec::<FpVar<Fr>>::new_witness(ark_relations::ns!(cs, "siblings"), || Ok(path.siblings.as_slice()))?;
+    match value {
+        Some(value) => {
+            let value_var = FpVar::new_witness(ark_relations::ns!(cs, "value"), || Ok(value))?;
+            smt_membership_gadget(&params_var, &root_var, &key_var, &value_var, &siblings_var)?;
+        }
+        None => smt_non_membership_gadget(&params_var, &root_var, &key_var, &siblings_var)?,
+    }
+
+    cs.finalize();
+
+    Ok((cs.is_satisfied()?, cs.num_constraints()))
+}
+
+pub fn test_sparse_merkle_tree() -> Result<(), SynthesisError> {
+    println!("\n### Running test_sparse_merkle_tree()...");
+
+    let mut rng = ark_std::test_rng();
+
+    let mut tree = SparseMerkleTree::new(smt_parameters());
+    let empty_root = tree.root();
+
+    let keys: Vec<SparseMerkleKey> = (0..16).map(|_| random_key(&mut rng)).collect();
+    for (i, key) in keys.iter().enumerate() {
+        tree.insert(*key, Fr::from(i as u64));
+    }
+    let root = tree.root();
+
+    // The root only depends on the contents, not on the insertion order or history
+    let mut other = SparseMerkleTree::new(smt_parameters());
+    for (i, key) in keys.iter().enumerate().rev() {
+        other.insert(*key, Fr::from(100 + i as u64));
+        other.insert(*key, Fr::from(i as u64));
+    }
+    let extra = random_key(&mut rng);
+    other.insert(extra, Fr::from(1u64));
+    assert_ne!(other.root(), root);
+    assert_eq!(other.remove(&extra), Some(Fr::from(1u64)));
+    assert_eq!(other.root(), root);
+
+    // Height h uses key bit h: keys differing in bit 0 only are siblings at the leaf level,
+    // keys differing in bit 255 only sit in the two subtrees of the root
+    let mut leaf_neighbour = keys[0];
+    leaf_neighbour[0] ^= 0x01;
+    let mut root_neighbour = keys[0];
+    root_neighbour[31] ^= 0x80;
+    let absent = [random_key(&mut rng), leaf_neighbour, root_neighbour, [0u8; 32], [0xff; 32]];
+
+    for (i, key) in keys.iter().enumerate() {
+        let path = tree.prove(key);
+        assert_eq!(tree.get(key), Some(Fr::from(i as u64)));
+        assert!(path.verify_membership(&tree.params, &root, key, Fr::from(i as u64)));
+        assert!(!path.verify_membership(&tree.params, &root, key, Fr::from(i as u64 + 1)));
+        assert!(!path.verify_non_membership(&tree.params, &root, key));
+    }
+    for key in absent.iter() {
+        let path = tree.prove(key);
+        assert_eq!(tree.get(key), None);
+        assert!(path.verify_non_membership(&tree.params, &root, key));
+        assert!(!path.verify_membership(&tree.params, &root, key, Fr::zero()));
+    }
+
+    // In-circuit: membership of present keys and absence of the others hold, the converse
+    // statements and statements against another root do not
+    let (satisfied, membership_constraints) = check_smt(&tree, root, &keys[3], Some(Fr::from(3u64)))?;
+    assert!(satisfied);
+    let (satisfied, non_membership_constraints) = check_smt(&tree, root, &absent[1], None)?;
+    assert!(satisfied);
+    for key in absent.iter() {
+        assert!(check_smt(&tree, root, key, None)?.0);
+        assert!(!check_smt(&tree, root, key, Some(Fr::zero()))?.0);
+    }
+    assert!(!check_smt(&tree, root, &keys[3], None)?.0);
+    assert!(!check_smt(&tree, root, &keys[3], Some(Fr::from(4u64)))?.0);
+    assert!(!check_smt(&tree, empty_root, &keys[3], Some(Fr::from(3u64)))?.0);
+
+    // Both leaves of a leaf-level pair present: each is the first sibling of the other
+    let mut pair = tree.clone();
+    pair.insert(leaf_neighbour, Fr::from(16u64));
+    let pair_root = pair.root();
+    assert_eq!(pair.prove(&leaf_neighbour).siblings[0], leaf_hash(&pair.params, &keys[0], Fr::from(0u64)));
+    assert_eq!(pair.prove(&keys[0]).siblings[0], leaf_hash(&pair.params, &leaf_neighbour, Fr::from(16u64)));
+    assert!(check_smt(&pair, pair_root, &keys[0], Some(Fr::from(0u64)))?.0);
+    assert!(check_smt(&pair, pair_root, &leaf_neighbour, Some(Fr::from(16u64)))?.0);
+    assert!(!check_smt(&pair, pair_root, &leaf_neighbour, None)?.0);
+
+    println!("depth {}: membership {} constraints, non-membership {} constraints", SMT_DEPTH, membership_constraints, non_membership_constraints);
+
+    Ok(())
+}
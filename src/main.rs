@@ -10,15 +10,52 @@ fn main() {
     //let _ = gadgets::test_fq_var();
     //let _ = gadgets::test_GVar();
     //let _ = gadgets::test_pairing();
+    //let _ = gadgets::test_pairing_costs();
     //let _ = gadgets::test_sha256_crh();
+    //let _ = gadgets::test_sha256_vectors();
+    //let _ = gadgets::test_blake2s_crh();
     //let _ = gadgets::test_pedersen_crh();
+    //let _ = gadgets::test_pedersen_window_sweep();
+    //let _ = gadgets::test_bowe_hopwood_crh();
+    //let _ = gadgets::test_poseidon_crh();
+    //let _ = gadgets::test_crh_costs();
+    //let _ = gadgets::test_mimc_crh();
+    //let _ = gadgets::test_hash_to_curve();
+    //let _ = gadgets::test_keccak256();
+    //let _ = gadgets::test_pedersen_commitment();
+    //let _ = gadgets::test_merkle_tree();
+    //let _ = gadgets::test_sparse_merkle_tree();
+    //let _ = gadgets::test_schnorr_signature();
+    //let _ = gadgets::test_blake2s_schnorr_signature();
+    //let _ = gadgets::test_eddsa_signature();
+    //let _ = gadgets::test_range_check();
     //let _ = gadgets::test_add();
     //let _ = gadgets::test_mul();
     //let _ = gadgets::test_mul_without_reduce();
 
     // *** WITH PROOF ***
     //let _ = proofs::test_sha256_crh_with_proof();
+    //let _ = proofs::test_blake2s_crh_with_proof();
+    //let _ = proofs::test_pedersen_crh_with_proof();
+    //let _ = proofs::test_poseidon_crh_with_proof();
+    //let _ = proofs::test_mimc_crh_with_proof();
+    //let _ = proofs::test_crh_cost_matrix();
+    //let _ = proofs::test_sha256_chain_with_proof();
+    //let _ = proofs::test_sha256_disclosure_with_proof();
+    //let _ = proofs::test_pedersen_commitment_with_proof();
+    //let _ = proofs::test_merkle_membership_with_proof();
+    //let _ = proofs::test_sparse_merkle_with_proof();
+    //let _ = proofs::test_merkle_update_with_proof();
+    //let _ = proofs::test_rollup_with_proof();
+    //let _ = proofs::test_join_split_with_proof();
+    //let _ = proofs::test_schnorr_signature_with_proof();
+    //let _ = proofs::test_eddsa_batch_with_proof();
     //let _ = proofs::test_fp_with_proof();
+    //let _ = proofs::test_pairing_fixed_g2_with_proof();
+    //let _ = proofs::test_pairing_digest_with_proof();
+    //let _ = proofs::test_bls_signature_with_proof();
+    //let _ = proofs::test_bls_committee_costs();
+    //let _ = proofs::test_bls_committee_with_proof();
     
     let _  = proofs::test_pairing_with_proof();
 }
@@ -0,0 +1,182 @@
+use ark_relations::r1cs::{ConstraintSystem, ConstraintSystemRef, SynthesisError, ConstraintSynthesizer};
+use ark_bls12_377::{
+    Fq as Fq_bls,
+    G1Projective,
+    G2Projective,
+};
+use ark_bw6_761::{
+    Fr as Fr_bw,
+    BW6_761,
+};
+use ark_std::{
+    rand::{SeedableRng, RngCore},
+    UniformRand,
+};
+use ark_r1cs_std::{
+    alloc::{AllocVar, AllocationMode},
+    eq::EqGadget,
+    fields::FieldVar,
+    groups::CurveVar,
+    pairing::PairingVar,
+};
+use ark_ec::Group;
+use ark_snark::{
+    CircuitSpecificSetupSNARK,
+    SNARK,
+};
+
+use crate::utils::bls::{self, BlsKeyPair, hash_to_g1, g1_to_field_elements, g2_to_field_elements};
+
+type Bls12G1Var = ark_bls12_377::constraints::G1Var;
+type Bls12G2Var = ark_bls12_377::constraints::G2Var;
+type Bls12TargetVar = ark_bls12_377::constraints::Fq12Var;
+type Bls12PairingVar = ark_bls12_377::constraints::PairingVar;
+
+// Proves e(sig, g2) == e(H(m), pk) over BLS12-377 inside BW6-761.
+// pk and H(m) are public inputs, the signature is allocated with `signature_mode`
+#[derive(Clone, Debug)]
+pub struct BlsSignatureCircuit {
+    pub pk: Option<G2Projective>,
+    pub msg_hash: Option<G1Projective>,
+    pub signature: Option<G1Projective>,
+    pub signature_mode: AllocationMode,
+}
+
+impl Default for BlsSignatureCircuit {
+    fn default() -> BlsSignatureCircuit {
+        let mut rng = ark_std::test_rng();
+        let keypair = BlsKeyPair::generate(&mut rng);
+        let msg = b"default";
+        BlsSignatureCircuit {
+            pk: Some(keypair.pk),
+            msg_hash: hash_to_g1(msg),
+            signature: Some(keypair.sign(msg)),
+            signature_mode: AllocationMode::Witness,
+        }
+    }
+}
+
+impl BlsSignatureCircuit {
+    // Public inputs in allocation order: pk, H(m) and (if public) the signature
+    pub fn public_inputs(&self) -> Vec<Fq_bls> {
+        let mut inputs = g2_to_field_elements(&self.pk.unwrap());
+        inputs.extend(g1_to_field_elements(&self.msg_hash.unwrap()));
+        if self.signature_mode == AllocationMode::Input {
+            inputs.extend(g1_to_field_elements(&self.signature.unwrap()));
+        }
+        inputs
+    }
+}
+
+impl ConstraintSynthesizer<Fr_bw> for BlsSignatureCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr_bw>) -> ark_relations::r1cs::Result<()> {
+        let pk_var = Bls12G2Var::new_input(
+            ark_relations::ns!(cs, "pk"),
+            || self.pk.ok_or(SynthesisError::AssignmentMissing),
+        )?;
+        let msg_hash_var = Bls12G1Var::new_input(
+            ark_relations::ns!(cs, "msg_hash"),
+            || self.msg_hash.ok_or(SynthesisError::AssignmentMissing),
+        )?;
+        let signature_var = Bls12G1Var::new_variable(
+            ark_relations::ns!(cs, "signature"),
+            || self.signature.ok_or(SynthesisError::AssignmentMissing),
+            self.signature_mode,
+        )?;
+        // The generator is a constant, so its line coefficients are computed natively
+        let generator_var = Bls12G2Var::new_constant(
+            ark_relations::ns!(cs, "g2"),
+            G2Projective::generator(),
+        )?;
+
+        enforce_bls_verification(&pk_var, &msg_hash_var, &signature_var, &generator_var)
+    }
+}
+
+// e(sig, g2) * e(-H(m), pk) == 1, sharing a single final exponentiation
+pub fn enforce_bls_verification(
+    pk_var: &Bls12G2Var,
+    msg_hash_var: &Bls12G1Var,
+    signature_var: &Bls12G1Var,
+    generator_var: &Bls12G2Var,
+) -> Result<(), SynthesisError> {
+    let g1_prep = [
+        Bls12PairingVar::prepare_g1(signature_var)?,
+        Bls12PairingVar::prepare_g1(&msg_hash_var.negate()?)?,
+    ];
+    let g2_prep = [
+        Bls12PairingVar::prepare_g2(generator_var)?,
+        Bls12PairingVar::prepare_g2(pk_var)?,
+    ];
+    let product = Bls12PairingVar::product_of_pairings(&g1_prep, &g2_prep)?;
+
+    product.enforce_equal(&Bls12TargetVar::one())
+}
+
+pub fn test_bls_signature_with_proof() {
+    println!("\n### Running test_bls_signature_with_proof()...");
+
+    use ark_groth16::Groth16;
+
+    let mut rng = ark_std::rand::rngs::StdRng::seed_from_u64(ark_std::test_rng().next_u64());
+
+    let msg = b"hello";
+    let keypair = BlsKeyPair::generate(&mut rng);
+    let signature = keypair.sign(msg);
+    let msg_hash = hash_to_g1(msg).unwrap();
+
+    // Forgery: a valid signature on the same message under a different key
+    let forged_signature = BlsKeyPair::generate(&mut rng).sign(msg);
+
+    assert!(bls::verify(&keypair.pk, msg, &signature));
+    assert!(!bls::verify(&keypair.pk, msg, &forged_signature));
+    assert!(!bls::verify(&keypair.pk, b"bye", &signature));
+
+    for signature_mode in [AllocationMode::Witness, AllocationMode::Input] {
+        println!("Signature allocated as {:?}", signature_mode);
+
+        let circuit_default = BlsSignatureCircuit {
+            signature_mode,
+            ..BlsSignatureCircuit::default()
+        };
+
+        let (pk, vk) = Groth16::<BW6_761>::setup(circuit_default, &mut rng).unwrap();
+
+        let circuit = BlsSignatureCircuit {
+            pk: Some(keypair.pk),
+            msg_hash: Some(msg_hash),
+            signature: Some(signature),
+            signature_mode,
+        };
+
+        let proof = Groth16::<BW6_761>::prove(&pk, circuit.clone(), &mut rng).unwrap();
+        assert!(Groth16::<BW6_761>::verify(&vk, circuit.public_inputs().as_slice(), &proof).unwrap());
+
+        // The same proof must not verify for another message
+        let other_circuit = BlsSignatureCircuit {
+            msg_hash: hash_to_g1(b"bye"),
+            ..circuit.clone()
+        };
+        assert!(!Groth16::<BW6_761>::verify(&vk, other_circuit.public_inputs().as_slice(), &proof).unwrap());
+
+        // A forged signature does not satisfy the circuit...
+        let forged_circuit = BlsSignatureCircuit {
+            signature: Some(forged_signature),
+            ..circuit.clone()
+        };
+        let cs = ConstraintSystem::<Fr_bw>::new_ref();
+        forged_circuit.clone().generate_constraints(cs.clone()).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+
+        // ...and when public, it cannot be swapped into the inputs of a valid proof
+        if signature_mode == AllocationMode::Input {
+            assert!(!Groth16::<BW6_761>::verify(&vk, forged_circuit.public_inputs().as_slice(), &proof).unwrap());
+
+            let random_circuit = BlsSignatureCircuit {
+                signature: Some(G1Projective::rand(&mut rng)),
+                ..circuit.clone()
+            };
+            assert!(!Groth16::<BW6_761>::verify(&vk, random_circuit.public_inputs().as_slice(), &proof).unwrap());
+        }
+    }
+}
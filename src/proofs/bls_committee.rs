@@ -0,0 +1,227 @@
+use std::cmp::Ordering;
+
+use ark_relations::r1cs::{ConstraintSystem, ConstraintSystemRef, SynthesisError, ConstraintSynthesizer};
+use ark_bls12_377::{
+    Fq as Fq_bls,
+    G1Projective,
+    G2Projective,
+};
+use ark_bw6_761::{
+    Fr as Fr_bw,
+    BW6_761,
+};
+use ark_std::rand::{SeedableRng, RngCore};
+use ark_r1cs_std::{
+    alloc::AllocVar,
+    boolean::Boolean,
+    fields::{fp::FpVar, FieldVar},
+    groups::CurveVar,
+};
+use ark_ec::Group;
+use ark_snark::{
+    CircuitSpecificSetupSNARK,
+    SNARK,
+};
+
+use crate::proofs::bls::enforce_bls_verification;
+use crate::utils::bls::{
+    self,
+    BlsKeyPair,
+    hash_to_g1,
+    aggregate_public_keys,
+    aggregate_signatures,
+    g1_to_field_elements,
+    g2_to_field_elements,
+};
+use crate::utils::is_satisfied;
+
+type Bls12G1Var = ark_bls12_377::constraints::G1Var;
+type Bls12G2Var = ark_bls12_377::constraints::G2Var;
+
+// Verifies an aggregate BLS signature from the members of `committee` selected by `bitmap`,
+// and enforces that at least `threshold` members took part.
+// Committee keys, bitmap and H(m) are public inputs; the aggregate signature is a witness.
+#[derive(Clone, Debug)]
+pub struct BlsCommitteeCircuit {
+    pub committee: Vec<G2Projective>,
+    pub bitmap: Vec<bool>,
+    pub threshold: usize,
+    pub msg_hash: Option<G1Projective>,
+    pub aggregate_signature: Option<G1Projective>,
+}
+
+impl BlsCommitteeCircuit {
+    // Placeholder circuit for setup: a committee of `committee_size` random keys, all signing
+    pub fn blank(committee_size: usize, threshold: usize) -> BlsCommitteeCircuit {
+        let mut rng = ark_std::test_rng();
+        let msg = b"default";
+        let keypairs: Vec<_> = (0..committee_size).map(|_| BlsKeyPair::generate(&mut rng)).collect();
+        let signatures: Vec<_> = keypairs.iter().map(|kp| kp.sign(msg)).collect();
+        BlsCommitteeCircuit {
+            committee: keypairs.iter().map(|kp| kp.pk).collect(),
+            bitmap: vec![true; committee_size],
+            threshold,
+            msg_hash: hash_to_g1(msg),
+            aggregate_signature: Some(aggregate_signatures(&signatures)),
+        }
+    }
+
+    // Public inputs in allocation order: committee keys, bitmap and H(m)
+    pub fn public_inputs(&self) -> Vec<Fq_bls> {
+        let mut inputs: Vec<Fq_bls> = self.committee.iter().flat_map(g2_to_field_elements).collect();
+        inputs.extend(self.bitmap.iter().map(|&bit| Fq_bls::from(bit)));
+        inputs.extend(g1_to_field_elements(&self.msg_hash.unwrap()));
+        inputs
+    }
+}
+
+impl ConstraintSynthesizer<Fr_bw> for BlsCommitteeCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr_bw>) -> ark_relations::r1cs::Result<()> {
+        // One bit per member
+        if self.committee.len() != self.bitmap.len() {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+
+        let committee_var = self.committee.iter().map(|pk| {
+            Bls12G2Var::new_input(ark_relations::ns!(cs, "committee"), || Ok(*pk))
+        }).collect::<Result<Vec<_>, _>>()?;
+        let bitmap_var = Vec::<Boolean<Fr_bw>>::new_input(
+            ark_relations::ns!(cs, "bitmap"),
+            || Ok(self.bitmap.clone()),
+        )?;
+        let msg_hash_var = Bls12G1Var::new_input(
+            ark_relations::ns!(cs, "msg_hash"),
+            || self.msg_hash.ok_or(SynthesisError::AssignmentMissing),
+        )?;
+        let signature_var = Bls12G1Var::new_witness(
+            ark_relations::ns!(cs, "aggregate_signature"),
+            || self.aggregate_signature.ok_or(SynthesisError::AssignmentMissing),
+        )?;
+        let generator_var = Bls12G2Var::new_constant(
+            ark_relations::ns!(cs, "g2"),
+            G2Projective::generator(),
+        )?;
+
+        // Participation threshold on the popcount of the bitmap
+        let mut participants = FpVar::<Fr_bw>::zero();
+        for bit in bitmap_var.iter() {
+            participants += FpVar::from(bit.clone());
+        }
+        let threshold_var = FpVar::constant(Fr_bw::from(self.threshold as u64));
+        participants.enforce_cmp(&threshold_var, Ordering::Greater, true)?;
+
+        // Aggregate public key of the selected members
+        let mut aggregate_pk_var = Bls12G2Var::zero();
+        for (pk_var, bit) in committee_var.iter().zip(bitmap_var.iter()) {
+            aggregate_pk_var += bit.select(pk_var, &Bls12G2Var::zero())?;
+        }
+
+        enforce_bls_verification(&aggregate_pk_var, &msg_hash_var, &signature_var, &generator_var)
+    }
+}
+
+pub fn test_bls_committee_costs() {
+    println!("\n### Running test_bls_committee_costs()...");
+
+    println!("{:>6} | {:>12} | {:>12} | {:>12}", "N", "constraints", "instance", "witness");
+    for committee_size in [1, 4, 8, 16, 32, 64] {
+        let circuit = BlsCommitteeCircuit::blank(committee_size, committee_size);
+
+        let cs = ConstraintSystem::<Fr_bw>::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        assert!(cs.is_satisfied().unwrap());
+
+        cs.finalize();
+
+        println!(
+            "{:>6} | {:>12} | {:>12} | {:>12}",
+            committee_size,
+            cs.num_constraints(),
+            cs.num_instance_variables(),
+            cs.num_witness_variables(),
+        );
+    }
+}
+
+pub fn test_bls_committee_with_proof() {
+    println!("\n### Running test_bls_committee_with_proof()...");
+
+    use ark_groth16::Groth16;
+
+    let mut rng = ark_std::rand::rngs::StdRng::seed_from_u64(ark_std::test_rng().next_u64());
+
+    const COMMITTEE_SIZE: usize = 8;
+    const THRESHOLD: usize = 6;
+
+    let msg = b"block header";
+    let keypairs: Vec<_> = (0..COMMITTEE_SIZE).map(|_| BlsKeyPair::generate(&mut rng)).collect();
+    let committee: Vec<_> = keypairs.iter().map(|kp| kp.pk).collect();
+
+    // Members 2 and 5 do not sign
+    let bitmap: Vec<bool> = (0..COMMITTEE_SIZE).map(|i| i != 2 && i != 5).collect();
+    let signatures: Vec<_> = keypairs
+        .iter()
+        .zip(&bitmap)
+        .filter(|(_, bit)| **bit)
+        .map(|(kp, _)| kp.sign(msg))
+        .collect();
+    let aggregate_signature = aggregate_signatures(&signatures);
+
+    assert!(bls::verify(&aggregate_public_keys(&committee, &bitmap), msg, &aggregate_signature));
+
+    let (pk, vk) = Groth16::<BW6_761>::setup(BlsCommitteeCircuit::blank(COMMITTEE_SIZE, THRESHOLD), &mut rng).unwrap();
+
+    let circuit = BlsCommitteeCircuit {
+        committee: committee.clone(),
+        bitmap: bitmap.clone(),
+        threshold: THRESHOLD,
+        msg_hash: hash_to_g1(msg),
+        aggregate_signature: Some(aggregate_signature),
+    };
+
+    let proof = Groth16::<BW6_761>::prove(&pk, circuit.clone(), &mut rng).unwrap();
+    assert!(Groth16::<BW6_761>::verify(&vk, circuit.public_inputs().as_slice(), &proof).unwrap());
+
+    // Claiming that a non-signer took part must fail
+    let mut wrong_bitmap = bitmap.clone();
+    wrong_bitmap[2] = true;
+    let wrong_circuit = BlsCommitteeCircuit {
+        bitmap: wrong_bitmap,
+        ..circuit.clone()
+    };
+    assert!(!Groth16::<BW6_761>::verify(&vk, wrong_circuit.public_inputs().as_slice(), &proof).unwrap());
+
+    assert!(!is_satisfied(wrong_circuit).unwrap().0);
+
+    // A valid aggregate signature from fewer than THRESHOLD members does not satisfy the circuit
+    let few_bitmap: Vec<bool> = (0..COMMITTEE_SIZE).map(|i| i < THRESHOLD - 1).collect();
+    let few_signatures: Vec<_> = keypairs[..THRESHOLD - 1].iter().map(|kp| kp.sign(msg)).collect();
+    let few_circuit = BlsCommitteeCircuit {
+        bitmap: few_bitmap,
+        aggregate_signature: Some(aggregate_signatures(&few_signatures)),
+        ..circuit.clone()
+    };
+    assert!(bls::verify(
+        &aggregate_public_keys(&committee, &few_circuit.bitmap),
+        msg,
+        &few_circuit.aggregate_signature.unwrap(),
+    ));
+
+    assert!(!is_satisfied(few_circuit).unwrap().0);
+
+    // A bitmap of the wrong length is an error from the prover, not a panic
+    let mut short_bitmap = bitmap.clone();
+    short_bitmap.pop();
+    let short_circuit = BlsCommitteeCircuit {
+        bitmap: short_bitmap,
+        ..circuit.clone()
+    };
+    assert!(matches!(Groth16::<BW6_761>::prove(&pk, short_circuit, &mut rng), Err(SynthesisError::Unsatisfiable)));
+
+    // The same proof does not verify for another message
+    let other_circuit = BlsCommitteeCircuit {
+        msg_hash: hash_to_g1(b"other block header"),
+        ..circuit.clone()
+    };
+    assert!(!Groth16::<BW6_761>::verify(&vk, other_circuit.public_inputs().as_slice(), &proof).unwrap());
+}
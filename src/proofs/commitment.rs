@@ -0,0 +1,142 @@
+use ark_relations::r1cs::{ConstraintSystem, ConstraintSystemRef, SynthesisError, ConstraintSynthesizer};
+use ark_bls12_377::{
+    Fr,
+    Bls12_377,
+};
+use ark_std::{
+    rand::{SeedableRng, RngCore},
+    UniformRand,
+};
+use ark_snark::{
+    CircuitSpecificSetupSNARK,
+    SNARK,
+};
+use ark_r1cs_std::{
+    alloc::AllocVar,
+    eq::EqGadget,
+    uint8::UInt8,
+};
+use ark_crypto_primitives::commitment::{
+    pedersen::{self, Randomness},
+    CommitmentGadget,
+    CommitmentScheme,
+};
+use ark_ed_on_bls12_377::{
+    EdwardsAffine,
+    EdwardsProjective as JubJub,
+    Fr as ScalarF,
+    constraints::EdwardsVar,
+};
+
+use crate::gadgets::{PedersenCommitment, PedersenCommitmentGadget};
+
+// Knowledge of an opening (message, r) of a Pedersen commitment.
+// The commitment point (x, y) is the public input; message and randomness are witnesses.
+#[derive(Clone)]
+pub struct PedersenOpeningCircuit {
+    pub params: pedersen::Parameters<JubJub>,
+    pub message: Option<Vec<u8>>,
+    pub randomness: Option<Randomness<JubJub>>,
+    pub commitment: Option<EdwardsAffine>,
+}
+
+impl PedersenOpeningCircuit {
+    pub fn new(params: pedersen::Parameters<JubJub>, message: Vec<u8>, randomness: Randomness<JubJub>) -> Self {
+        let commitment = PedersenCommitment::commit(&params, &message, &randomness).unwrap();
+        PedersenOpeningCircuit {
+            params,
+            message: Some(message),
+            randomness: Some(randomness),
+            commitment: Some(commitment),
+        }
+    }
+
+    // Placeholder circuit for setup.
+    // NOTE: The message length must be fixed, since the padding makes trailing zeros free
+    pub fn blank(params: pedersen::Parameters<JubJub>, message_len: usize) -> Self {
+        PedersenOpeningCircuit::new(params, vec![0; message_len], Randomness(ScalarF::from(0u64)))
+    }
+
+    pub fn public_inputs(&self) -> Vec<Fr> {
+        let commitment = self.commitment.unwrap();
+        vec![commitment.x, commitment.y]
+    }
+}
+
+impl ConstraintSynthesizer<Fr> for PedersenOpeningCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> ark_relations::r1cs::Result<()> {
+        let params_var = pedersen::constraints::ParametersVar::new_constant(
+            ark_relations::ns!(cs, "Commitment Parameters"),
+            &self.params,
+        )?;
+        let message_var = UInt8::new_witness_vec(
+            ark_relations::ns!(cs, "message"),
+            self.message.ok_or(SynthesisError::AssignmentMissing)?.as_slice(),
+        )?;
+        let randomness_var = pedersen::constraints::RandomnessVar::new_witness(
+            ark_relations::ns!(cs, "randomness"),
+            || self.randomness.ok_or(SynthesisError::AssignmentMissing),
+        )?;
+        let commitment_var = EdwardsVar::new_input(
+            ark_relations::ns!(cs, "commitment"),
+            || self.commitment.ok_or(SynthesisError::AssignmentMissing),
+        )?;
+
+        let result_var = PedersenCommitmentGadget::commit(&params_var, &message_var, &randomness_var)?;
+
+        result_var.enforce_equal(&commitment_var)?;
+
+        Ok(())
+    }
+}
+
+pub fn test_pedersen_commitment_with_proof() {
+    println!("\n### Running test_pedersen_commitment_with_proof()...");
+
+    use ark_groth16::Groth16;
+
+    let mut rng = ark_std::rand::rngs::StdRng::seed_from_u64(ark_std::test_rng().next_u64());
+
+    const MESSAGE_LEN: usize = 32;
+
+    // The parameters are circuit constants, so setup and proving share them
+    let params = PedersenCommitment::setup(&mut rng).unwrap();
+    let (pk, vk) = Groth16::<Bls12_377>::setup(PedersenOpeningCircuit::blank(params.clone(), MESSAGE_LEN), &mut rng).unwrap();
+
+    let message: Vec<u8> = (0..MESSAGE_LEN).map(|_| u8::rand(&mut rng)).collect();
+    let randomness = Randomness::<JubJub>(ScalarF::rand(&mut rng));
+    let circuit = PedersenOpeningCircuit::new(params.clone(), message.clone(), randomness.clone());
+
+    let proof = Groth16::<Bls12_377>::prove(&pk, circuit.clone(), &mut rng).unwrap();
+    assert!(Groth16::<Bls12_377>::verify(&vk, circuit.public_inputs().as_slice(), &proof).unwrap());
+
+    // The proof is tied to this commitment: not to a commitment to the same message
+    // under other randomness, nor to another message
+    let rerandomized = PedersenOpeningCircuit::new(params.clone(), message.clone(), Randomness(ScalarF::rand(&mut rng)));
+    assert!(!Groth16::<Bls12_377>::verify(&vk, rerandomized.public_inputs().as_slice(), &proof).unwrap());
+
+    let mut other_message = message.clone();
+    other_message[0] ^= 1;
+    let other = PedersenOpeningCircuit::new(params.clone(), other_message.clone(), randomness.clone());
+    assert!(!Groth16::<Bls12_377>::verify(&vk, other.public_inputs().as_slice(), &proof).unwrap());
+
+    // Binding: wrong openings of the committed point do not satisfy the circuit
+    let wrong_openings = [
+        (other_message, randomness.clone()),
+        (message.clone(), Randomness(randomness.0 + ScalarF::from(1u64))),
+    ];
+    for (wrong_message, wrong_randomness) in wrong_openings {
+        let wrong_circuit = PedersenOpeningCircuit {
+            message: Some(wrong_message),
+            randomness: Some(wrong_randomness),
+            ..circuit.clone()
+        };
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        wrong_circuit.generate_constraints(cs.clone()).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+
+    // Swapped coordinates
+    let commitment = circuit.commitment.unwrap();
+    assert!(!Groth16::<Bls12_377>::verify(&vk, &[commitment.y, commitment.x], &proof).unwrap());
+}
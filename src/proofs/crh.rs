@@ -15,59 +15,87 @@ use ark_r1cs_std::{
     uint8::UInt8,
     ToBytesGadget,
     eq::EqGadget,
+    alloc::AllocVar,
+    fields::fp::FpVar,
 };
 use ark_crypto_primitives::crh::{
     sha256::{constraints::{Sha256Gadget, DigestVar}, Sha256}, 
+    pedersen,
+    poseidon,
     CRHScheme, 
     CRHSchemeGadget,
 };
+use ark_ed_on_bls12_377::{
+    EdwardsAffine,
+    EdwardsProjective as JubJub,
+    constraints::EdwardsVar,
+};
 use ark_ff::ToConstraintField;
+use ark_crypto_primitives::{sponge::poseidon::PoseidonConfig, Error};
 
-pub fn test_sha256_crh_with_proof() {
-    println!("\n### Running test_sha256_crh_with_proof()...");
+use crate::gadgets::{HashFunction, HashParameters, blake2s_gadget, mimc_hash_gadget, mimc_feistel_hash_gadget};
+use crate::utils::mimc::{mimc_feistel_hash, mimc_hash, MimcParameters, MIMC_SEED};
+use crate::utils::poseidon::poseidon_parameters;
 
-    use ark_groth16::Groth16;
+use std::time::Instant;
+use blake2::{Blake2s256, Digest};
 
-    let mut rng = ark_std::rand::rngs::StdRng::seed_from_u64(ark_std::test_rng().next_u64());
+// Knowledge of a SHA-256 preimage. The digest bytes are the public input.
+#[derive(Clone)]
+pub struct Sha256Circuit {
+    pub input: Option<Vec<u8>>,
+    pub output: Option<Vec<u8>>,
+}
 
-    #[derive(Clone)]
-    struct Sha256Circuit {
-        input: Option<Vec<u8>>,
-        output: Option<Vec<u8>>,
-    }
-    
-    impl Default for Sha256Circuit {
-        fn default() -> Sha256Circuit {
-            Sha256Circuit {
-                // NOTE: The input length must be fixed: fix real input or apply padding
-                input: Some(vec![0; 5]),
-                output: Some(vec![0; 32]),
-            }
+impl Default for Sha256Circuit {
+    fn default() -> Sha256Circuit {
+        Sha256Circuit {
+            // NOTE: The input length must be fixed: fix real input or apply padding
+            input: Some(vec![0; 5]),
+            output: Some(vec![0; 32]),
         }
     }
+}
 
-    impl ConstraintSynthesizer<Fr> for Sha256Circuit {
-        fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> ark_relations::r1cs::Result<()> {
-            let input_var = UInt8::new_witness_vec(
-                ark_relations::ns!(cs, "input"), 
-                self.input.ok_or(SynthesisError::AssignmentMissing).unwrap().as_slice(),
-            )?;
-            let output_var = UInt8::new_input_vec(
-                ark_relations::ns!(cs, "output"),
-                self.output.ok_or(SynthesisError::AssignmentMissing).unwrap().as_slice(),
-            )?;
-            let mut sha_var = Sha256Gadget::<Fr>::default();
-            sha_var.update(&input_var)?;
-            let sha_var_output = sha_var.finalize().unwrap().to_bytes().unwrap();
-
-            output_var.enforce_equal(sha_var_output.as_slice())?;
-
-            // cs.finalize() not needed here (specified inside prove() method)
-            // same happens with OptimizationGoal
-
-            Ok(())
-        }
+impl Sha256Circuit {
+    // Enforces SHA256(input) == output and returns the input bytes, so that other circuits
+    // can add statements about the preimage
+    pub fn enforce_preimage(self, cs: ConstraintSystemRef<Fr>) -> Result<Vec<UInt8<Fr>>, SynthesisError> {
+        let input_var = UInt8::new_witness_vec(
+            ark_relations::ns!(cs, "input"), 
+            self.input.ok_or(SynthesisError::AssignmentMissing)?.as_slice(),
+        )?;
+        let output_var = UInt8::new_input_vec(
+            ark_relations::ns!(cs, "output"),
+            self.output.ok_or(SynthesisError::AssignmentMissing)?.as_slice(),
+        )?;
+        let mut sha_var = Sha256Gadget::<Fr>::default();
+        sha_var.update(&input_var)?;
+        let sha_var_output = sha_var.finalize()?.to_bytes()?;
+
+        output_var.enforce_equal(sha_var_output.as_slice())?;
+
+        Ok(input_var)
+    }
+}
+
+impl ConstraintSynthesizer<Fr> for Sha256Circuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> ark_relations::r1cs::Result<()> {
+        self.enforce_preimage(cs)?;
+
+        // cs.finalize() not needed here (specified inside prove() method)
+        // same happens with OptimizationGoal
+
+        Ok(())
     }
+}
+
+pub fn test_sha256_crh_with_proof() {
+    println!("\n### Running test_sha256_crh_with_proof()...");
+
+    use ark_groth16::Groth16;
+
+    let mut rng = ark_std::rand::rngs::StdRng::seed_from_u64(ark_std::test_rng().next_u64());
 
     let circuit_default = Sha256Circuit::default();
 
@@ -90,4 +118,451 @@ pub fn test_sha256_crh_with_proof() {
     // I think the circuit computation (from Vec<u8>) and the public_input are not being converted in the same way...
     assert!(Groth16::<Bls12_377>::verify(&vk, output_field.as_slice(), &proof).unwrap());
 
-}
\ No newline at end of file
+}
+
+#[derive(Clone)]
+pub struct PedersenWindow;
+impl pedersen::Window for PedersenWindow {
+    const WINDOW_SIZE: usize = 128;
+    const NUM_WINDOWS: usize = 4;
+}
+
+type PedersenCRH = pedersen::CRH<JubJub, PedersenWindow>;
+type PedersenCRHGadget = pedersen::constraints::CRHGadget<JubJub, EdwardsVar, PedersenWindow>;
+
+// Knowledge of a preimage of a Pedersen hash. The output point (x, y) is the public input.
+#[derive(Clone)]
+struct PedersenCircuit {
+    params: pedersen::Parameters<JubJub>,
+    input: Option<Vec<u8>>,
+    output: Option<EdwardsAffine>,
+}
+
+impl Default for PedersenCircuit {
+    fn default() -> PedersenCircuit {
+        let mut rng = ark_std::test_rng();
+        let params = PedersenCRH::setup(&mut rng).unwrap();
+        // NOTE: The input length must be fixed: fix real input or apply padding
+        let input = vec![0; 5];
+        let output = PedersenCRH::evaluate(&params, input.as_slice()).unwrap();
+        PedersenCircuit {
+            params,
+            input: Some(input),
+            output: Some(output),
+        }
+    }
+}
+
+impl ConstraintSynthesizer<Fr> for PedersenCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> ark_relations::r1cs::Result<()> {
+        let params_var = pedersen::constraints::CRHParametersVar::<JubJub, EdwardsVar>::new_constant(
+            ark_relations::ns!(cs, "CRH Parameters"),
+            &self.params,
+        )?;
+        let input_var = UInt8::new_witness_vec(
+            ark_relations::ns!(cs, "input"),
+            self.input.ok_or(SynthesisError::AssignmentMissing)?.as_slice(),
+        )?;
+        let output_var = EdwardsVar::new_input(
+            ark_relations::ns!(cs, "output"),
+            || self.output.ok_or(SynthesisError::AssignmentMissing),
+        )?;
+
+        let result_var = PedersenCRHGadget::evaluate(&params_var, &input_var)?;
+
+        result_var.enforce_equal(&output_var)?;
+
+        Ok(())
+    }
+}
+
+pub fn test_pedersen_crh_with_proof() {
+    println!("\n### Running test_pedersen_crh_with_proof()...");
+
+    use ark_groth16::Groth16;
+
+    let mut rng = ark_std::rand::rngs::StdRng::seed_from_u64(ark_std::test_rng().next_u64());
+
+    // The parameters are circuit constants, so setup and proving share them
+    let circuit_default = PedersenCircuit::default();
+    let params = circuit_default.params.clone();
+
+    let (pk, vk) = Groth16::<Bls12_377>::setup(circuit_default, &mut rng).unwrap();
+
+    let input = String::from("hello");
+    let output = PedersenCRH::evaluate(&params, input.as_bytes()).unwrap();
+
+    let circuit = PedersenCircuit {
+        params: params.clone(),
+        input: Some(input.into_bytes()),
+        output: Some(output),
+    };
+
+    let proof = Groth16::<Bls12_377>::prove(&pk, circuit, &mut rng).unwrap();
+
+    // EdwardsVar::new_input allocates the affine coordinates x and y, in that order
+    let output_field = output.to_field_elements().unwrap();
+    assert_eq!(output_field, vec![output.x, output.y]);
+    assert!(Groth16::<Bls12_377>::verify(&vk, output_field.as_slice(), &proof).unwrap());
+
+    // Wrong encodings of the same point
+    assert!(!Groth16::<Bls12_377>::verify(&vk, &[output.y, output.x], &proof).unwrap());
+    assert!(!Groth16::<Bls12_377>::verify(&vk, &[-output.x, output.y], &proof).unwrap());
+    assert!(!Groth16::<Bls12_377>::verify(&vk, &[output.x], &proof).unwrap_or(false));
+
+    // Hash of another input
+    let other_output = PedersenCRH::evaluate(&params, "hellp".as_bytes()).unwrap();
+    assert!(!Groth16::<Bls12_377>::verify(&vk, &[other_output.x, other_output.y], &proof).unwrap());
+
+    // Random coordinates
+    assert!(!Groth16::<Bls12_377>::verify(&vk, &[Fr::rand(&mut rng), Fr::rand(&mut rng)], &proof).unwrap());
+}
+
+type PoseidonCRH = poseidon::CRH<Fr>;
+type PoseidonCRHGadget = poseidon::constraints::CRHGadget<Fr>;
+
+// Knowledge of a preimage of a Poseidon hash. The digest is the only public input.
+#[derive(Clone)]
+struct PoseidonCircuit {
+    params: PoseidonConfig<Fr>,
+    input: Option<Vec<Fr>>,
+    output: Option<Fr>,
+}
+
+impl Default for PoseidonCircuit {
+    fn default() -> PoseidonCircuit {
+        let params = poseidon_parameters::<Fr>(2);
+        // NOTE: The number of input elements must be fixed
+        let input = vec![Fr::from(0u64); 4];
+        let output = PoseidonCRH::evaluate(&params, input.as_slice()).unwrap();
+        PoseidonCircuit {
+            params,
+            input: Some(input),
+            output: Some(output),
+        }
+    }
+}
+
+impl ConstraintSynthesizer<Fr> for PoseidonCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> ark_relations::r1cs::Result<()> {
+        let params_var = poseidon::constraints::CRHParametersVar::<Fr>::new_constant(
+            ark_relations::ns!(cs, "CRH Parameters"),
+            &self.params,
+        )?;
+        let input_var = Vec::<FpVar<Fr>>::new_witness(
+            ark_relations::ns!(cs, "input"),
+            || self.input.ok_or(SynthesisError::AssignmentMissing),
+        )?;
+        let output_var = FpVar::new_input(
+            ark_relations::ns!(cs, "output"),
+            || self.output.ok_or(SynthesisError::AssignmentMissing),
+        )?;
+
+        let result_var = PoseidonCRHGadget::evaluate(&params_var, &input_var)?;
+
+        result_var.enforce_equal(&output_var)?;
+
+        Ok(())
+    }
+}
+
+pub fn test_poseidon_crh_with_proof() {
+    println!("\n### Running test_poseidon_crh_with_proof()...");
+
+    use ark_groth16::Groth16;
+
+    let mut rng = ark_std::rand::rngs::StdRng::seed_from_u64(ark_std::test_rng().next_u64());
+
+    let circuit_default = PoseidonCircuit::default();
+    let params = circuit_default.params.clone();
+
+    let cs = ConstraintSystem::<Fr>::new_ref();
+    circuit_default.clone().generate_constraints(cs.clone()).unwrap();
+    println!("Number of constraints: {}", cs.num_constraints());
+
+    let (pk, vk) = Groth16::<Bls12_377>::setup(circuit_default, &mut rng).unwrap();
+
+    let input: Vec<Fr> = (0..4).map(|_| Fr::rand(&mut rng)).collect();
+    let output = PoseidonCRH::evaluate(&params, input.as_slice()).unwrap();
+
+    let circuit = PoseidonCircuit {
+        params: params.clone(),
+        input: Some(input.clone()),
+        output: Some(output),
+    };
+
+    let proof = Groth16::<Bls12_377>::prove(&pk, circuit, &mut rng).unwrap();
+    assert!(Groth16::<Bls12_377>::verify(&vk, &[output], &proof).unwrap());
+
+    // Hash of another input
+    let mut other_input = input.clone();
+    other_input.swap(0, 1);
+    let other_output = PoseidonCRH::evaluate(&params, other_input.as_slice()).unwrap();
+    assert!(!Groth16::<Bls12_377>::verify(&vk, &[other_output], &proof).unwrap());
+
+    // A wrong preimage does not satisfy the circuit
+    let wrong_circuit = PoseidonCircuit {
+        params,
+        input: Some(other_input),
+        output: Some(output),
+    };
+    let cs = ConstraintSystem::<Fr>::new_ref();
+    wrong_circuit.generate_constraints(cs.clone()).unwrap();
+    assert!(!cs.is_satisfied().unwrap());
+}
+
+// Knowledge of a preimage under any of the byte-oriented CRHs of gadgets::crh.
+// The digest bytes are the public input, packed into field elements by UInt8::new_input_vec.
+#[derive(Clone)]
+struct HashCircuit {
+    function: HashFunction,
+    params: HashParameters,
+    input: Option<Vec<u8>>,
+    digest: Option<Vec<u8>>,
+}
+
+impl HashCircuit {
+    // The digest is computed natively, so inputs the hash does not accept (longer than the
+    // Pedersen windows) are rejected here rather than during setup or proving
+    fn new(function: HashFunction, params: HashParameters, input: Vec<u8>) -> Result<HashCircuit, Error> {
+        let digest = function.evaluate(&params, &input)?;
+        Ok(HashCircuit { function, params, input: Some(input), digest: Some(digest) })
+    }
+}
+
+impl ConstraintSynthesizer<Fr> for HashCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> ark_relations::r1cs::Result<()> {
+        let input_var = UInt8::new_witness_vec(
+            ark_relations::ns!(cs, "input"),
+            self.input.ok_or(SynthesisError::AssignmentMissing)?.as_slice(),
+        )?;
+        let digest_var = UInt8::new_input_vec(
+            ark_relations::ns!(cs, "digest"),
+            self.digest.ok_or(SynthesisError::AssignmentMissing)?.as_slice(),
+        )?;
+
+        // Synthesis errors are passed through; an input the hash does not accept (not built
+        // with HashCircuit::new) has no satisfying assignment
+        let result_var = self.function
+            .evaluate_gadget(&self.params, &input_var)
+            .map_err(|e| match e.downcast::<SynthesisError>() {
+                Ok(e) => *e,
+                Err(_) => SynthesisError::Unsatisfiable,
+            })?;
+
+        result_var.enforce_equal(&digest_var)?;
+
+        Ok(())
+    }
+}
+
+pub fn test_crh_cost_matrix() {
+    println!("\n### Running test_crh_cost_matrix()...");
+
+    use ark_groth16::Groth16;
+
+    const NATIVE_HASHES: u32 = 100;
+
+    let mut rng = ark_std::rand::rngs::StdRng::seed_from_u64(ark_std::test_rng().next_u64());
+    let params = HashParameters::setup(&mut rng);
+
+    // Longer than the Pedersen windows: an error when building the circuit, and an error
+    // (not a panic) from setup for a circuit built by hand
+    let too_long = vec![0u8; 1025];
+    assert!(HashCircuit::new(HashFunction::Pedersen, params.clone(), too_long.clone()).is_err());
+    let unchecked = HashCircuit {
+        function: HashFunction::Pedersen,
+        params: params.clone(),
+        input: Some(too_long),
+        digest: Some(vec![0; 64]),
+    };
+    assert!(matches!(Groth16::<Bls12_377>::setup(unchecked, &mut rng), Err(SynthesisError::Unsatisfiable)));
+
+    println!(
+        "{:>6} | {:>12} | {:>12} | {:>12} | {:>12} | {:>12}",
+        "bytes", "hash", "constraints", "witness", "native (us)", "prove (ms)",
+    );
+    for len in [32, 64, 128, 256, 512, 1024] {
+        let input: Vec<u8> = (0..len).map(|_| u8::rand(&mut rng)).collect();
+
+        for function in HashFunction::ALL {
+            let start = Instant::now();
+            for _ in 0..NATIVE_HASHES {
+                function.evaluate(&params, &input).unwrap();
+            }
+            let native_time = start.elapsed() / NATIVE_HASHES;
+
+            let circuit = HashCircuit::new(function, params.clone(), input.clone()).unwrap();
+            let digest = circuit.digest.clone().unwrap();
+
+            let cs = ConstraintSystem::<Fr>::new_ref();
+            cs.set_optimization_goal(OptimizationGoal::Constraints);
+            circuit.clone().generate_constraints(cs.clone()).unwrap();
+            assert!(cs.is_satisfied().unwrap());
+            cs.finalize();
+
+            let (pk, vk) = Groth16::<Bls12_377>::setup(circuit.clone(), &mut rng).unwrap();
+
+            let start = Instant::now();
+            let proof = Groth16::<Bls12_377>::prove(&pk, circuit, &mut rng).unwrap();
+            let prove_time = start.elapsed();
+
+            assert!(Groth16::<Bls12_377>::verify(&vk, digest.to_field_elements().unwrap().as_slice(), &proof).unwrap());
+
+            println!(
+                "{:>6} | {:>12} | {:>12} | {:>12} | {:>12.1} | {:>12}",
+                len,
+                function.name(),
+                cs.num_constraints(),
+                cs.num_witness_variables(),
+                native_time.as_secs_f64() * 1e6,
+                prove_time.as_millis(),
+            );
+        }
+    }
+}
+
+// Same statement as the SHA-256 circuit, with BLAKE2s-256
+#[derive(Clone)]
+struct Blake2sCircuit {
+    input: Option<Vec<u8>>,
+    output: Option<Vec<u8>>,
+}
+
+impl Default for Blake2sCircuit {
+    fn default() -> Blake2sCircuit {
+        Blake2sCircuit {
+            // NOTE: The input length must be fixed: fix real input or apply padding
+            input: Some(vec![0; 5]),
+            output: Some(vec![0; 32]),
+        }
+    }
+}
+
+impl ConstraintSynthesizer<Fr> for Blake2sCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> ark_relations::r1cs::Result<()> {
+        let input_var = UInt8::new_witness_vec(
+            ark_relations::ns!(cs, "input"),
+            self.input.ok_or(SynthesisError::AssignmentMissing)?.as_slice(),
+        )?;
+        let output_var = UInt8::new_input_vec(
+            ark_relations::ns!(cs, "output"),
+            self.output.ok_or(SynthesisError::AssignmentMissing)?.as_slice(),
+        )?;
+
+        let result_var = blake2s_gadget(&input_var)?;
+
+        output_var.enforce_equal(result_var.as_slice())?;
+
+        Ok(())
+    }
+}
+
+pub fn test_blake2s_crh_with_proof() {
+    println!("\n### Running test_blake2s_crh_with_proof()...");
+
+    use ark_groth16::Groth16;
+
+    let mut rng = ark_std::rand::rngs::StdRng::seed_from_u64(ark_std::test_rng().next_u64());
+
+    let (pk, vk) = Groth16::<Bls12_377>::setup(Blake2sCircuit::default(), &mut rng).unwrap();
+
+    let input = String::from("hello");
+    let output = Blake2s256::digest(input.as_bytes()).to_vec();
+
+    let circuit = Blake2sCircuit {
+        input: Some(input.into_bytes()),
+        output: Some(output.clone()),
+    };
+
+    let proof = Groth16::<Bls12_377>::prove(&pk, circuit, &mut rng).unwrap();
+
+    // UInt8::new_input_vec packs the digest bytes as ToConstraintField does
+    let output_field = output.to_field_elements().unwrap();
+    assert!(Groth16::<Bls12_377>::verify(&vk, output_field.as_slice(), &proof).unwrap());
+
+    // Digest of another input
+    let other_output = Blake2s256::digest(b"hellp").to_vec();
+    assert!(!Groth16::<Bls12_377>::verify(&vk, other_output.to_field_elements().unwrap().as_slice(), &proof).unwrap());
+
+    // The SHA-256 digest of the same input
+    let sha_output = Sha256::evaluate(&(), "hello".as_bytes()).unwrap();
+    assert!(!Groth16::<Bls12_377>::verify(&vk, sha_output.to_field_elements().unwrap().as_slice(), &proof).unwrap());
+}
+
+// Knowledge of a preimage of a MiMC hash (Miyaguchi-Preneel or Feistel sponge).
+// The digest is the only public input.
+#[derive(Clone, Debug)]
+struct MimcCircuit {
+    params: MimcParameters<Fr>,
+    feistel: bool,
+    input: Vec<Fr>,
+    output: Fr,
+}
+
+impl MimcCircuit {
+    fn new(params: MimcParameters<Fr>, feistel: bool, input: Vec<Fr>) -> MimcCircuit {
+        let output = if feistel {
+            mimc_feistel_hash(&params, &input)
+        } else {
+            mimc_hash(&params, &input)
+        };
+        MimcCircuit { params, feistel, input, output }
+    }
+}
+
+impl ConstraintSynthesizer<Fr> for MimcCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> ark_relations::r1cs::Result<()> {
+        let input_var = Vec::<FpVar<Fr>>::new_witness(
+            ark_relations::ns!(cs, "input"),
+            || Ok(self.input),
+        )?;
+        let output_var = FpVar::new_input(
+            ark_relations::ns!(cs, "output"),
+            || Ok(self.output),
+        )?;
+
+        let result = if self.feistel {
+            mimc_feistel_hash_gadget(&self.params, &input_var)?
+        } else {
+            mimc_hash_gadget(&self.params, &input_var)?
+        };
+
+        output_var.enforce_equal(&result)?;
+
+        Ok(())
+    }
+}
+
+pub fn test_mimc_crh_with_proof() {
+    println!("\n### Running test_mimc_crh_with_proof()...");
+
+    use ark_groth16::Groth16;
+
+    let mut rng = ark_std::rand::rngs::StdRng::seed_from_u64(ark_std::test_rng().next_u64());
+
+    for feistel in [false, true] {
+        let params = if feistel {
+            MimcParameters::<Fr>::feistel(MIMC_SEED, 7)
+        } else {
+            // x^7 is not a permutation of Fr, the cipher uses x^11
+            MimcParameters::<Fr>::cipher(MIMC_SEED, 11)
+        };
+
+        // NOTE: The number of input elements must be fixed
+        let default_circuit = MimcCircuit::new(params.clone(), feistel, vec![Fr::from(0u64); 2]);
+
+        let (pk, vk) = Groth16::<Bls12_377>::setup(default_circuit, &mut rng).unwrap();
+
+        let circuit = MimcCircuit::new(params.clone(), feistel, vec![Fr::rand(&mut rng), Fr::rand(&mut rng)]);
+
+        let proof = Groth16::<Bls12_377>::prove(&pk, circuit.clone(), &mut rng).unwrap();
+
+        assert!(Groth16::<Bls12_377>::verify(&vk, &[circuit.output], &proof).unwrap());
+
+        // Digest of another input
+        let other_circuit = MimcCircuit::new(params, feistel, vec![circuit.input[1], circuit.input[0]]);
+        assert!(!Groth16::<Bls12_377>::verify(&vk, &[other_circuit.output], &proof).unwrap());
+        println!("{} proof verified", if feistel { "MiMC-Feistel" } else { "MiMC-11" });
+    }
+}
@@ -0,0 +1,225 @@
+use std::ops::Range;
+
+use ark_relations::r1cs::{ConstraintSystem, ConstraintSystemRef, SynthesisError, ConstraintSynthesizer};
+use ark_bls12_377::{
+    Fr,
+    Bls12_377,
+};
+use ark_std::rand::{CryptoRng, RngCore, SeedableRng};
+use ark_snark::{
+    CircuitSpecificSetupSNARK,
+    SNARK,
+};
+use ark_r1cs_std::{
+    uint8::UInt8,
+    eq::EqGadget,
+};
+use ark_crypto_primitives::crh::{sha256::Sha256, CRHScheme};
+use ark_ff::ToConstraintField;
+use ark_groth16::{Groth16, Proof, ProvingKey, VerifyingKey};
+
+use crate::proofs::crh::Sha256Circuit;
+
+// Knowledge of a message with a public SHA-256 digest, revealing the bytes in `ranges`.
+// The ranges are fixed at setup. Public inputs: the digest, then the disclosed bytes
+// concatenated in range order (both packed by UInt8::new_input_vec).
+#[derive(Clone)]
+pub struct Sha256DisclosureCircuit {
+    pub sha: Sha256Circuit,
+    pub ranges: Vec<Range<usize>>,
+    pub disclosed: Option<Vec<u8>>,
+}
+
+impl Sha256DisclosureCircuit {
+    pub fn new(message: &[u8], ranges: &[Range<usize>]) -> Result<Sha256DisclosureCircuit, SynthesisError> {
+        check_ranges(message.len(), ranges)?;
+        Ok(Sha256DisclosureCircuit {
+            sha: Sha256Circuit {
+                input: Some(message.to_vec()),
+                output: Some(Sha256::evaluate(&(), message).unwrap()),
+            },
+            ranges: ranges.to_vec(),
+            disclosed: Some(disclosed_bytes(message, ranges)),
+        })
+    }
+
+    // Placeholder circuit for setup
+    pub fn blank(message_len: usize, ranges: &[Range<usize>]) -> Result<Sha256DisclosureCircuit, SynthesisError> {
+        Sha256DisclosureCircuit::new(&vec![0; message_len], ranges)
+    }
+}
+
+// Ranges must be non-empty and within the message
+pub fn check_ranges(message_len: usize, ranges: &[Range<usize>]) -> Result<(), SynthesisError> {
+    if ranges.iter().all(|range| range.start < range.end && range.end <= message_len) {
+        Ok(())
+    } else {
+        Err(SynthesisError::Unsatisfiable)
+    }
+}
+
+impl ConstraintSynthesizer<Fr> for Sha256DisclosureCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> ark_relations::r1cs::Result<()> {
+        let input_var = self.sha.enforce_preimage(cs.clone())?;
+        check_ranges(input_var.len(), &self.ranges)?;
+
+        let disclosed_var = UInt8::new_input_vec(
+            ark_relations::ns!(cs, "disclosed"),
+            self.disclosed.ok_or(SynthesisError::AssignmentMissing)?.as_slice(),
+        )?;
+        let selected_var: Vec<_> = self.ranges.iter().flat_map(|range| input_var[range.clone()].to_vec()).collect();
+        if selected_var.len() != disclosed_var.len() {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+
+        selected_var.enforce_equal(&disclosed_var)?;
+
+        Ok(())
+    }
+}
+
+pub fn disclosed_bytes(message: &[u8], ranges: &[Range<usize>]) -> Vec<u8> {
+    ranges.iter().flat_map(|range| message[range.clone()].to_vec()).collect()
+}
+
+// What the verifier sees: the digest of the hidden message and the revealed bytes
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Disclosure {
+    pub digest: Vec<u8>,
+    pub ranges: Vec<Range<usize>>,
+    pub disclosed: Vec<u8>,
+}
+
+impl Disclosure {
+    pub fn public_inputs(&self) -> Vec<Fr> {
+        let mut inputs: Vec<Fr> = self.digest.to_field_elements().unwrap();
+        let disclosed: Vec<Fr> = self.disclosed.to_field_elements().unwrap();
+        inputs.extend(disclosed);
+        inputs
+    }
+
+    // The bytes revealed for every range, in order
+    pub fn revealed(&self) -> Vec<&[u8]> {
+        let mut offset = 0;
+        self.ranges.iter().map(|range| {
+            let part = &self.disclosed[offset..offset + range.len()];
+            offset += range.len();
+            part
+        }).collect()
+    }
+}
+
+// The ranges are not public inputs: they are bound to the verifying key instead
+#[derive(Clone)]
+pub struct DisclosureVerifyingKey {
+    pub vk: VerifyingKey<Bls12_377>,
+    pub ranges: Vec<Range<usize>>,
+}
+
+pub fn setup_disclosure<R: RngCore + CryptoRng>(
+    message_len: usize,
+    ranges: &[Range<usize>],
+    rng: &mut R,
+) -> Result<(ProvingKey<Bls12_377>, DisclosureVerifyingKey), SynthesisError> {
+    let (pk, vk) = Groth16::<Bls12_377>::setup(Sha256DisclosureCircuit::blank(message_len, ranges)?, rng)?;
+    Ok((pk, DisclosureVerifyingKey { vk, ranges: ranges.to_vec() }))
+}
+
+pub fn prove_disclosure<R: RngCore + CryptoRng>(
+    pk: &ProvingKey<Bls12_377>,
+    message: &[u8],
+    ranges: &[Range<usize>],
+    rng: &mut R,
+) -> Result<(Proof<Bls12_377>, Disclosure), SynthesisError> {
+    let circuit = Sha256DisclosureCircuit::new(message, ranges)?;
+    let disclosure = Disclosure {
+        digest: circuit.sha.output.clone().unwrap(),
+        ranges: ranges.to_vec(),
+        disclosed: circuit.disclosed.clone().unwrap(),
+    };
+    let proof = Groth16::<Bls12_377>::prove(pk, circuit, rng)?;
+    Ok((proof, disclosure))
+}
+
+// The circuit fixes the ranges, so `disclosure.ranges` must be those given at setup
+pub fn verify_disclosure(
+    vk: &DisclosureVerifyingKey,
+    disclosure: &Disclosure,
+    proof: &Proof<Bls12_377>,
+) -> Result<bool, SynthesisError> {
+    if disclosure.ranges != vk.ranges {
+        return Ok(false);
+    }
+    Groth16::<Bls12_377>::verify(&vk.vk, disclosure.public_inputs().as_slice(), proof)
+}
+
+pub fn test_sha256_disclosure_with_proof() {
+    println!("\n### Running test_sha256_disclosure_with_proof()...");
+
+    let mut rng = ark_std::rand::rngs::StdRng::seed_from_u64(ark_std::test_rng().next_u64());
+
+    let message = b"name=Alice Liddell;birth=1852-05-04;country=UK;account=GB29NWBK60161331926819";
+    let ranges = [25..35, 36..46];
+
+    let (pk, vk) = setup_disclosure(message.len(), &ranges, &mut rng).unwrap();
+    let (proof, disclosure) = prove_disclosure(&pk, message, &ranges, &mut rng).unwrap();
+
+    assert_eq!(disclosure.revealed(), vec![b"1852-05-04".as_slice(), b"country=UK".as_slice()]);
+    assert!(verify_disclosure(&vk, &disclosure, &proof).unwrap());
+    for part in disclosure.revealed() {
+        println!("disclosed: {}", String::from_utf8_lossy(part));
+    }
+
+    // Tampering with a disclosed byte
+    let mut tampered = disclosure.clone();
+    tampered.disclosed[3] = b'3';
+    assert!(!verify_disclosure(&vk, &tampered, &proof).unwrap());
+
+    // Relabelled ranges of the same total length: the disclosed bytes alone would still verify,
+    // but revealed() would report them at the wrong positions
+    let relabelled = Disclosure { ranges: vec![0..10, 36..46], ..disclosure.clone() };
+    assert!(Groth16::<Bls12_377>::verify(&vk.vk, relabelled.public_inputs().as_slice(), &proof).unwrap());
+    assert!(!verify_disclosure(&vk, &relabelled, &proof).unwrap());
+
+    // The digest of another message with the same disclosed bytes
+    let mut other_message = message.to_vec();
+    other_message[5] = b'B';
+    let other = Disclosure {
+        digest: Sha256::evaluate(&(), other_message.as_slice()).unwrap(),
+        ..disclosure.clone()
+    };
+    assert!(!verify_disclosure(&vk, &other, &proof).unwrap());
+
+    // A prover cannot claim bytes that are not in the hashed message
+    let wrong_circuit = Sha256DisclosureCircuit {
+        disclosed: Some(b"1852-05-05country=UK".to_vec()),
+        ..Sha256DisclosureCircuit::new(message, &ranges).unwrap()
+    };
+    let cs = ConstraintSystem::<Fr>::new_ref();
+    wrong_circuit.generate_constraints(cs.clone()).unwrap();
+    assert!(!cs.is_satisfied().unwrap());
+
+    // Empty, reversed or out-of-bounds ranges are errors, not panics
+    let invalid_ranges = [
+        vec![Range { start: 5, end: 5 }],
+        vec![Range { start: 10, end: 3 }],
+        vec![25..35, 70..message.len() + 1],
+    ];
+    for ranges in invalid_ranges.iter() {
+        assert!(matches!(setup_disclosure(message.len(), ranges, &mut rng), Err(SynthesisError::Unsatisfiable)));
+        assert!(matches!(prove_disclosure(&pk, message, ranges, &mut rng), Err(SynthesisError::Unsatisfiable)));
+    }
+
+    // Another choice of ranges needs its own setup: proofs do not transfer between layouts
+    let other_ranges = [0..10, 47..54];
+    let (other_pk, other_vk) = setup_disclosure(message.len(), &other_ranges, &mut rng).unwrap();
+    let (other_proof, other_disclosure) = prove_disclosure(&other_pk, message, &other_ranges, &mut rng).unwrap();
+    assert!(verify_disclosure(&other_vk, &other_disclosure, &other_proof).unwrap());
+    assert!(!verify_disclosure(&vk, &other_disclosure, &other_proof).unwrap_or(false));
+
+    // Nothing disclosed: a plain preimage proof
+    let (empty_pk, empty_vk) = setup_disclosure(message.len(), &[], &mut rng).unwrap();
+    let (empty_proof, empty_disclosure) = prove_disclosure(&empty_pk, message, &[], &mut rng).unwrap();
+    assert!(empty_disclosure.disclosed.is_empty());
+    assert!(verify_disclosure(&empty_vk, &empty_disclosure, &empty_proof).unwrap());
+}
@@ -0,0 +1,158 @@
+use std::time::Instant;
+
+use ark_relations::r1cs::{ConstraintSystemRef, ConstraintSynthesizer};
+use ark_bls12_377::{
+    Fr,
+    Bls12_377,
+};
+use ark_std::rand::{SeedableRng, RngCore};
+use ark_snark::{
+    CircuitSpecificSetupSNARK,
+    SNARK,
+};
+use ark_r1cs_std::{
+    alloc::AllocVar,
+    boolean::Boolean,
+    eq::EqGadget,
+    uint8::UInt8,
+};
+use ark_ed_on_bls12_377::{EdwardsAffine, Fr as ScalarF, constraints::EdwardsVar};
+use ark_ff::ToConstraintField;
+
+use crate::gadgets::{EddsaHashVar, EddsaSignatureVar, eddsa_verify_gadget};
+use crate::utils::eddsa::{eddsa_verify, EddsaHash, EddsaKeyPair, EddsaSignature};
+use crate::utils::poseidon::poseidon_parameters;
+use crate::utils::is_satisfied;
+
+// Batch verification of EdDSA signatures in one circuit.
+// Each key (x, y) and message (packed as UInt8::new_input_vec) is a public input;
+// the signatures are the witness.
+#[derive(Clone)]
+pub struct EddsaBatchCircuit {
+    pub hash: EddsaHash,
+    pub pks: Vec<EdwardsAffine>,
+    pub messages: Vec<Vec<u8>>,
+    pub signatures: Vec<EddsaSignature>,
+}
+
+impl EddsaBatchCircuit {
+    // Placeholder circuit for setup.
+    // NOTE: The batch size and the message length are fixed
+    pub fn blank(hash: EddsaHash, batch_size: usize, message_len: usize) -> Self {
+        EddsaBatchCircuit {
+            hash,
+            pks: vec![EdwardsAffine::default(); batch_size],
+            messages: vec![vec![0; message_len]; batch_size],
+            signatures: vec![EddsaSignature { r: EdwardsAffine::default(), s: ScalarF::from(0u64) }; batch_size],
+        }
+    }
+
+    pub fn public_inputs(&self) -> Vec<Fr> {
+        let mut inputs = vec![];
+        for (pk, message) in self.pks.iter().zip(self.messages.iter()) {
+            inputs.extend([pk.x, pk.y]);
+            let message: Vec<Fr> = message.to_field_elements().unwrap();
+            inputs.extend(message);
+        }
+        inputs
+    }
+}
+
+impl ConstraintSynthesizer<Fr> for EddsaBatchCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> ark_relations::r1cs::Result<()> {
+        let hash_var = EddsaHashVar::new_constant(cs.clone(), &self.hash)?;
+
+        for ((pk, message), signature) in self.pks.iter().zip(self.messages.iter()).zip(self.signatures.iter()) {
+            let pk_var = EdwardsVar::new_input(ark_relations::ns!(cs, "pk"), || Ok(*pk))?;
+            let message_var = UInt8::new_input_vec(ark_relations::ns!(cs, "message"), message)?;
+            let signature_var = EddsaSignatureVar::new_witness(ark_relations::ns!(cs, "signature"), || Ok(signature))?;
+
+            eddsa_verify_gadget(&hash_var, &pk_var, &message_var, &signature_var)?
+                .enforce_equal(&Boolean::TRUE)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn signed_batch(hash: &EddsaHash, key_pairs: &[EddsaKeyPair], message_len: usize, rng: &mut impl RngCore) -> EddsaBatchCircuit {
+    let messages: Vec<Vec<u8>> = key_pairs
+        .iter()
+        .map(|_| {
+            let mut message = vec![0; message_len];
+            rng.fill_bytes(&mut message);
+            message
+        })
+        .collect();
+    let signatures = key_pairs.iter().zip(messages.iter()).map(|(key_pair, message)| key_pair.sign(hash, message)).collect();
+
+    EddsaBatchCircuit {
+        hash: hash.clone(),
+        pks: key_pairs.iter().map(|key_pair| key_pair.pk).collect(),
+        messages,
+        signatures,
+    }
+}
+
+pub fn test_eddsa_batch_with_proof() {
+    println!("\n### Running test_eddsa_batch_with_proof()...");
+
+    use ark_groth16::Groth16;
+
+    let mut rng = ark_std::rand::rngs::StdRng::seed_from_u64(ark_std::test_rng().next_u64());
+
+    const MESSAGE_LEN: usize = 32;
+
+    let key_pairs: Vec<EddsaKeyPair> = (0..16).map(|_| EddsaKeyPair::generate(&mut rng)).collect();
+    let hashes = [
+        (EddsaHash::Sha256, vec![1, 4]),
+        (EddsaHash::Poseidon(poseidon_parameters::<Fr>(2)), vec![1, 4, 16]),
+    ];
+
+    println!("{}-byte messages", MESSAGE_LEN);
+    println!(
+        "{:>10} | {:>4} | {:>12} | {:>10} | {:>11} | {:>11}",
+        "hash", "N", "constraints", "prove (ms)", "verify (ms)", "sigs/s",
+    );
+    for (hash, batch_sizes) in hashes.iter() {
+        for &batch_size in batch_sizes {
+            let (pk, vk) = Groth16::<Bls12_377>::setup(EddsaBatchCircuit::blank(hash.clone(), batch_size, MESSAGE_LEN), &mut rng).unwrap();
+
+            let circuit = signed_batch(hash, &key_pairs[..batch_size], MESSAGE_LEN, &mut rng);
+            for ((pk, message), signature) in circuit.pks.iter().zip(circuit.messages.iter()).zip(circuit.signatures.iter()) {
+                assert!(eddsa_verify(hash, pk, message, signature));
+            }
+
+            let (satisfied, constraints) = is_satisfied(circuit.clone()).unwrap();
+            assert!(satisfied);
+
+            let start = Instant::now();
+            let proof = Groth16::<Bls12_377>::prove(&pk, circuit.clone(), &mut rng).unwrap();
+            let prove_time = start.elapsed();
+
+            let start = Instant::now();
+            assert!(Groth16::<Bls12_377>::verify(&vk, circuit.public_inputs().as_slice(), &proof).unwrap());
+            let verify_time = start.elapsed();
+
+            // The proof is tied to every message of the batch
+            let mut other = circuit.clone();
+            other.messages[batch_size - 1][0] ^= 1;
+            assert!(!Groth16::<Bls12_377>::verify(&vk, other.public_inputs().as_slice(), &proof).unwrap());
+
+            // One forged signature makes the whole batch unsatisfiable
+            let mut forged = circuit.clone();
+            forged.signatures[batch_size - 1].s += ScalarF::from(1u64);
+            assert!(!is_satisfied(forged).unwrap().0);
+
+            println!(
+                "{:>10} | {:>4} | {:>12} | {:>10} | {:>11} | {:>11.1}",
+                hash.name(),
+                batch_size,
+                constraints,
+                prove_time.as_millis(),
+                verify_time.as_millis(),
+                batch_size as f64 / prove_time.as_secs_f64(),
+            );
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use std::time::Instant;
+
+use ark_relations::r1cs::{ConstraintSystem, ConstraintSystemRef, SynthesisError, ConstraintSynthesizer};
+use ark_bls12_377::{
+    Fr,
+    Bls12_377,
+};
+use ark_std::{
+    rand::{SeedableRng, RngCore},
+    UniformRand,
+};
+use ark_snark::{
+    CircuitSpecificSetupSNARK,
+    SNARK,
+};
+use ark_r1cs_std::{
+    uint8::UInt8,
+    eq::EqGadget,
+    ToBytesGadget,
+};
+use ark_crypto_primitives::crh::{
+    sha256::{constraints::{Sha256Gadget, UnitVar}, Sha256},
+    CRHScheme,
+    CRHSchemeGadget,
+};
+use ark_ff::ToConstraintField;
+
+// h_0 = x, h_i = SHA256(h_{i-1})
+pub fn sha256_chain(input: &[u8], length: usize) -> Vec<u8> {
+    let mut h = input.to_vec();
+    for _ in 0..length {
+        h = Sha256::evaluate(&(), h).unwrap();
+    }
+    h
+}
+
+// Proves h_n = SHA256^n(x) for a private x. The chain length n and the length of x are
+// fixed at setup; h_n is the public input.
+#[derive(Clone, Debug)]
+pub struct Sha256ChainCircuit {
+    pub length: usize,
+    pub input: Option<Vec<u8>>,
+    pub output: Option<Vec<u8>>,
+}
+
+impl Sha256ChainCircuit {
+    pub fn new(input: Vec<u8>, length: usize) -> Sha256ChainCircuit {
+        Sha256ChainCircuit {
+            length,
+            output: Some(sha256_chain(&input, length)),
+            input: Some(input),
+        }
+    }
+
+    // Placeholder circuit for setup
+    pub fn blank(input_len: usize, length: usize) -> Sha256ChainCircuit {
+        Sha256ChainCircuit::new(vec![0; input_len], length)
+    }
+
+    pub fn public_inputs(&self) -> Vec<Fr> {
+        self.output.as_ref().unwrap().to_field_elements().unwrap()
+    }
+}
+
+impl ConstraintSynthesizer<Fr> for Sha256ChainCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> ark_relations::r1cs::Result<()> {
+        assert!(self.length > 0);
+
+        let input_var = UInt8::new_witness_vec(
+            ark_relations::ns!(cs, "input"),
+            self.input.ok_or(SynthesisError::AssignmentMissing)?.as_slice(),
+        )?;
+        let output_var = UInt8::new_input_vec(
+            ark_relations::ns!(cs, "output"),
+            self.output.ok_or(SynthesisError::AssignmentMissing)?.as_slice(),
+        )?;
+
+        let mut h_var = input_var;
+        for _ in 0..self.length {
+            h_var = Sha256Gadget::evaluate(&UnitVar::default(), &h_var)?.to_bytes()?;
+        }
+
+        output_var.enforce_equal(&h_var)?;
+
+        Ok(())
+    }
+}
+
+pub fn test_sha256_chain_with_proof() {
+    println!("\n### Running test_sha256_chain_with_proof()...");
+
+    use ark_groth16::Groth16;
+
+    let mut rng = ark_std::rand::rngs::StdRng::seed_from_u64(ark_std::test_rng().next_u64());
+
+    const INPUT_LEN: usize = 32;
+
+    println!(
+        "{:>4} | {:>12} | {:>12} | {:>12} | {:>12}",
+        "n", "constraints", "setup (ms)", "prove (ms)", "verify (ms)",
+    );
+    for length in [1, 2, 4, 8, 16] {
+        let input: Vec<u8> = (0..INPUT_LEN).map(|_| u8::rand(&mut rng)).collect();
+        let circuit = Sha256ChainCircuit::new(input.clone(), length);
+
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        circuit.clone().generate_constraints(cs.clone()).unwrap();
+        assert!(cs.is_satisfied().unwrap());
+
+        let start = Instant::now();
+        let (pk, vk) = Groth16::<Bls12_377>::setup(Sha256ChainCircuit::blank(INPUT_LEN, length), &mut rng).unwrap();
+        let setup_time = start.elapsed();
+
+        let start = Instant::now();
+        let proof = Groth16::<Bls12_377>::prove(&pk, circuit.clone(), &mut rng).unwrap();
+        let prove_time = start.elapsed();
+
+        let start = Instant::now();
+        assert!(Groth16::<Bls12_377>::verify(&vk, circuit.public_inputs().as_slice(), &proof).unwrap());
+        let verify_time = start.elapsed();
+
+        // Neighbouring links of the chain are not accepted
+        for other_length in [length - 1, length + 1] {
+            let other_output = sha256_chain(&input, other_length);
+            let other_inputs = other_output.to_field_elements().unwrap();
+            assert!(!Groth16::<Bls12_377>::verify(&vk, other_inputs.as_slice(), &proof).unwrap());
+        }
+
+        println!(
+            "{:>4} | {:>12} | {:>12} | {:>12} | {:>12}",
+            length,
+            cs.num_constraints(),
+            setup_time.as_millis(),
+            prove_time.as_millis(),
+            verify_time.as_millis(),
+        );
+    }
+
+    // A witness for a chain one step shorter does not satisfy the circuit
+    let input: Vec<u8> = (0..INPUT_LEN).map(|_| u8::rand(&mut rng)).collect();
+    let wrong_circuit = Sha256ChainCircuit {
+        output: Some(sha256_chain(&input, 3)),
+        ..Sha256ChainCircuit::new(input, 4)
+    };
+    let cs = ConstraintSystem::<Fr>::new_ref();
+    wrong_circuit.generate_constraints(cs.clone()).unwrap();
+    assert!(!cs.is_satisfied().unwrap());
+}
@@ -0,0 +1,578 @@
+use std::collections::HashSet;
+use std::time::Instant;
+
+use ark_relations::r1cs::{ConstraintSystemRef, SynthesisError, ConstraintSynthesizer};
+use ark_bls12_377::{
+    Fr,
+    Bls12_377,
+};
+use ark_std::{
+    rand::{CryptoRng, SeedableRng, RngCore},
+    UniformRand,
+};
+use ark_snark::{
+    CircuitSpecificSetupSNARK,
+    SNARK,
+};
+use ark_r1cs_std::{
+    alloc::AllocVar,
+    boolean::Boolean,
+    eq::EqGadget,
+    fields::{fp::FpVar, FieldVar},
+    uint8::UInt8,
+    ToBitsGadget,
+    ToConstraintFieldGadget,
+};
+use ark_crypto_primitives::{
+    crh::{CRHScheme, CRHSchemeGadget},
+    merkle_tree::{constraints::PathVar, Path},
+    prf::{Blake2s, PRF},
+    sponge::poseidon::PoseidonConfig,
+};
+use ark_ff::{ToConstraintField, Zero};
+use ark_groth16::{Groth16, Proof, VerifyingKey};
+
+use crate::gadgets::{
+    MerkleHash,
+    MerkleParameters,
+    PoseidonMerkle,
+    PoseidonCRH,
+    PoseidonCRHGadget,
+    PoseidonCRHParametersVar,
+    merkle_parameters_var,
+    blake2s_prf_gadget,
+    enforce_range_gadget,
+};
+use crate::utils::poseidon::poseidon_parameters;
+use crate::utils::is_satisfied;
+use super::merkle_update::MerkleState;
+
+// Sprout-style join-split: two input notes are spent and two output notes created.
+//
+//  note:       (a_pk, value, rho, r), value on 64 bits
+//  address:    a_pk = PRF_{a_sk}(0^256)                  (Blake2s PRF)
+//  commitment: cm = Poseidon(a_pk, value, rho, r)       (a_pk and rho packed 31 bytes per element)
+//  nullifier:  nf = PRF_{a_sk}(rho | 1 << 255)           (the top bit separates it from addresses)
+//
+// rho has 255 bits (its top bit is clear), otherwise rho and rho | 1 << 255 would have the
+// same nullifier. The circuit enforces it for input and output notes.
+//
+// The circuit shows that each input note is in the tree of commitments under the public root
+// (unless its value is 0, for dummy inputs), is owned by the spender and has the public
+// nullifier, that the output commitments are well formed, and that
+//  sum(inputs) + vpub_old == sum(outputs) + vpub_new.
+// Double spends are caught by the ledger: a note always has the same nullifier.
+// Output rho are public and the ledger rejects a rho it has already seen, so two notes never
+// share a nullifier (otherwise a payee could only spend one of two notes paid with the same rho).
+// Unlike Sprout, which derives them from the input nullifiers, this also covers deposits.
+
+pub const VALUE_BITS: usize = 64;
+pub const NUM_INPUTS: usize = 2;
+pub const NUM_OUTPUTS: usize = 2;
+
+type CommitmentTree = <PoseidonMerkle as MerkleHash>::P;
+
+pub fn address(a_sk: &[u8; 32]) -> [u8; 32] {
+    Blake2s::evaluate(a_sk, &[0u8; 32]).unwrap()
+}
+
+pub fn nullifier(a_sk: &[u8; 32], rho: &[u8; 32]) -> [u8; 32] {
+    let mut input = *rho;
+    input[31] |= 0x80;
+    Blake2s::evaluate(a_sk, &input).unwrap()
+}
+
+#[derive(Clone, Debug)]
+pub struct Note {
+    pub a_pk: [u8; 32],
+    pub value: u64,
+    pub rho: [u8; 32],
+    pub r: Fr,
+}
+
+impl Note {
+    pub fn new<R: RngCore>(a_pk: [u8; 32], value: u64, rng: &mut R) -> Self {
+        let mut rho = [0u8; 32];
+        rng.fill_bytes(&mut rho);
+        rho[31] &= 0x7f;
+        Note { a_pk, value, rho, r: Fr::rand(rng) }
+    }
+
+    pub fn commitment(&self, params: &PoseidonConfig<Fr>) -> Fr {
+        let mut input: Vec<Fr> = self.a_pk.to_field_elements().unwrap();
+        input.push(Fr::from(self.value));
+        let rho: Vec<Fr> = self.rho.to_field_elements().unwrap();
+        input.extend(rho);
+        input.push(self.r);
+        PoseidonCRH::evaluate(params, input).unwrap()
+    }
+}
+
+// The chain: the tree of note commitments, its past roots and the spent nullifiers
+pub struct Ledger {
+    pub commitment: PoseidonConfig<Fr>,
+    pub state: MerkleState<PoseidonMerkle>,
+    pub next_index: usize,
+    pub roots: Vec<Fr>,
+    pub nullifiers: HashSet<[u8; 32]>,
+    pub rhos: HashSet<[u8; 32]>,
+    pub vk: VerifyingKey<Bls12_377>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum LedgerError {
+    UnknownRoot,
+    DoubleSpend,
+    RepeatedRho,
+    InvalidProof,
+    TreeFull,
+}
+
+#[derive(Clone)]
+pub struct JoinSplitTransaction {
+    pub root: Fr,
+    pub nullifiers: Vec<[u8; 32]>,
+    pub commitments: Vec<Fr>,
+    pub rhos: Vec<[u8; 32]>,
+    pub vpub_old: u64,
+    pub vpub_new: u64,
+    pub proof: Proof<Bls12_377>,
+}
+
+impl JoinSplitTransaction {
+    // Same layout as JoinSplitCircuit::public_inputs
+    pub fn public_inputs(&self) -> Vec<Fr> {
+        let mut inputs = vec![self.root];
+        for nf in self.nullifiers.iter() {
+            let nf: Vec<Fr> = nf.to_field_elements().unwrap();
+            inputs.extend(nf);
+        }
+        inputs.extend(self.commitments.iter());
+        for rho in self.rhos.iter() {
+            let rho: Vec<Fr> = rho.to_field_elements().unwrap();
+            inputs.extend(rho);
+        }
+        inputs.push(Fr::from(self.vpub_old));
+        inputs.push(Fr::from(self.vpub_new));
+        inputs
+    }
+}
+
+impl Ledger {
+    pub fn new(params: MerkleParameters<CommitmentTree>, depth: usize, vk: VerifyingKey<Bls12_377>) -> Self {
+        let state = MerkleState::new(params, vec![vec![Fr::zero()]; 1 << depth]);
+        let roots = vec![state.root()];
+        Ledger {
+            commitment: poseidon_parameters::<Fr>(2),
+            state,
+            next_index: 0,
+            roots,
+            nullifiers: HashSet::new(),
+            rhos: HashSet::new(),
+            vk,
+        }
+    }
+
+    pub fn root(&self) -> Fr {
+        self.state.root()
+    }
+
+    pub fn path(&self, index: usize) -> Path<CommitmentTree> {
+        self.state.tree.generate_proof(index).unwrap()
+    }
+
+    // Appends commitments and returns their positions
+    fn append(&mut self, commitments: &[Fr]) -> Result<Vec<usize>, LedgerError> {
+        if self.next_index + commitments.len() > self.state.leaves.len() {
+            return Err(LedgerError::TreeFull);
+        }
+        let updates: Vec<(usize, Vec<Fr>)> = commitments
+            .iter()
+            .enumerate()
+            .map(|(i, cm)| (self.next_index + i, vec![*cm]))
+            .collect();
+        self.state.apply(&updates);
+        self.next_index += commitments.len();
+        self.roots.push(self.root());
+        Ok(updates.into_iter().map(|(index, _)| index).collect())
+    }
+
+    // Transparent deposit of a note (no proof, its value comes from outside the pool)
+    pub fn deposit(&mut self, note: &Note) -> Result<usize, LedgerError> {
+        if self.rhos.contains(&note.rho) {
+            return Err(LedgerError::RepeatedRho);
+        }
+        let index = self.append(&[note.commitment(&self.commitment)])?[0];
+        self.rhos.insert(note.rho);
+        Ok(index)
+    }
+
+    // Checks the anchor, the nullifiers, the output rho, the room left in the tree and the proof, then records
+    // the transaction: nothing is recorded if it is rejected.
+    // Returns the positions of the output notes.
+    pub fn submit(&mut self, tx: &JoinSplitTransaction) -> Result<Vec<usize>, LedgerError> {
+        if !self.roots.contains(&tx.root) {
+            return Err(LedgerError::UnknownRoot);
+        }
+        let distinct: HashSet<&[u8; 32]> = tx.nullifiers.iter().collect();
+        if distinct.len() != tx.nullifiers.len() || tx.nullifiers.iter().any(|nf| self.nullifiers.contains(nf)) {
+            return Err(LedgerError::DoubleSpend);
+        }
+        let distinct: HashSet<&[u8; 32]> = tx.rhos.iter().collect();
+        if distinct.len() != tx.rhos.len() || tx.rhos.iter().any(|rho| self.rhos.contains(rho)) {
+            return Err(LedgerError::RepeatedRho);
+        }
+        if self.next_index + tx.commitments.len() > self.state.leaves.len() {
+            return Err(LedgerError::TreeFull);
+        }
+        if !Groth16::<Bls12_377>::verify(&self.vk, tx.public_inputs().as_slice(), &tx.proof).unwrap() {
+            return Err(LedgerError::InvalidProof);
+        }
+        self.nullifiers.extend(tx.nullifiers.iter().copied());
+        self.rhos.extend(tx.rhos.iter().copied());
+        self.append(&tx.commitments)
+    }
+}
+
+// Native wallet: a spending key and the notes received at its address, with their positions
+pub struct Wallet {
+    pub a_sk: [u8; 32],
+    pub notes: Vec<(Note, usize)>,
+}
+
+impl Wallet {
+    pub fn new<R: RngCore>(rng: &mut R) -> Self {
+        let mut a_sk = [0u8; 32];
+        rng.fill_bytes(&mut a_sk);
+        Wallet { a_sk, notes: Vec::new() }
+    }
+
+    pub fn address(&self) -> [u8; 32] {
+        address(&self.a_sk)
+    }
+
+    pub fn balance(&self) -> u64 {
+        self.notes.iter().map(|(note, _)| note.value).sum()
+    }
+
+    // Spends the notes at `spent` (positions in self.notes, at most NUM_INPUTS, padded with
+    // dummy notes) into `outputs`, against the current root of `ledger`
+    pub fn spend<R: RngCore>(
+        &self,
+        ledger: &Ledger,
+        spent: &[usize],
+        outputs: Vec<Note>,
+        vpub_old: u64,
+        vpub_new: u64,
+        rng: &mut R,
+    ) -> JoinSplitCircuit {
+        let mut inputs: Vec<JoinSplitInput> = spent
+            .iter()
+            .map(|i| {
+                let (note, index) = &self.notes[*i];
+                JoinSplitInput { a_sk: self.a_sk, note: note.clone(), path: ledger.path(*index) }
+            })
+            .collect();
+        while inputs.len() < NUM_INPUTS {
+            inputs.push(JoinSplitInput {
+                a_sk: self.a_sk,
+                note: Note::new(self.address(), 0, rng),
+                path: ledger.path(0),
+            });
+        }
+        JoinSplitCircuit {
+            params: ledger.state.params.clone(),
+            commitment: ledger.commitment.clone(),
+            root: ledger.root(),
+            inputs,
+            outputs,
+            vpub_old,
+            vpub_new,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct JoinSplitInput {
+    pub a_sk: [u8; 32],
+    pub note: Note,
+    pub path: Path<CommitmentTree>,
+}
+
+// The root, the input nullifiers, the output commitments and rho, vpub_old and vpub_new are public inputs;
+// the notes, keys and paths are witnesses
+#[derive(Clone)]
+pub struct JoinSplitCircuit {
+    pub params: MerkleParameters<CommitmentTree>,
+    pub commitment: PoseidonConfig<Fr>,
+    pub root: Fr,
+    pub inputs: Vec<JoinSplitInput>,
+    pub outputs: Vec<Note>,
+    pub vpub_old: u64,
+    pub vpub_new: u64,
+}
+
+impl JoinSplitCircuit {
+    // Placeholder circuit for setup
+    pub fn blank<R: RngCore>(params: MerkleParameters<CommitmentTree>, depth: usize, rng: &mut R) -> Self {
+        let (_, path, root) = crate::gadgets::random_path::<PoseidonMerkle, _>(&params, depth, rng);
+        let inputs = (0..NUM_INPUTS)
+            .map(|_| JoinSplitInput { a_sk: [0u8; 32], note: Note::new([0u8; 32], 0, rng), path: path.clone() })
+            .collect();
+        let outputs = (0..NUM_OUTPUTS).map(|_| Note::new([0u8; 32], 0, rng)).collect();
+        JoinSplitCircuit {
+            params,
+            commitment: poseidon_parameters::<Fr>(2),
+            root,
+            inputs,
+            outputs,
+            vpub_old: 0,
+            vpub_new: 0,
+        }
+    }
+
+    pub fn nullifiers(&self) -> Vec<[u8; 32]> {
+        self.inputs.iter().map(|input| nullifier(&input.a_sk, &input.note.rho)).collect()
+    }
+
+    pub fn commitments(&self) -> Vec<Fr> {
+        self.outputs.iter().map(|note| note.commitment(&self.commitment)).collect()
+    }
+
+    pub fn rhos(&self) -> Vec<[u8; 32]> {
+        self.outputs.iter().map(|note| note.rho).collect()
+    }
+
+    pub fn prove<R: RngCore + CryptoRng>(&self, pk: &ark_groth16::ProvingKey<Bls12_377>, rng: &mut R) -> JoinSplitTransaction {
+        JoinSplitTransaction {
+            root: self.root,
+            nullifiers: self.nullifiers(),
+            commitments: self.commitments(),
+            rhos: self.rhos(),
+            vpub_old: self.vpub_old,
+            vpub_new: self.vpub_new,
+            proof: Groth16::<Bls12_377>::prove(pk, self.clone(), rng).unwrap(),
+        }
+    }
+}
+
+// Poseidon(a_pk, value, rho, r) in-circuit, as Note::commitment
+fn note_commitment_gadget(
+    params: &PoseidonCRHParametersVar,
+    a_pk: &[UInt8<Fr>],
+    value: &FpVar<Fr>,
+    rho: &[UInt8<Fr>],
+    r: &FpVar<Fr>,
+) -> Result<FpVar<Fr>, SynthesisError> {
+    let mut input = a_pk.to_constraint_field()?;
+    input.push(value.clone());
+    input.extend(rho.to_constraint_field()?);
+    input.push(r.clone());
+    PoseidonCRHGadget::evaluate(params, &input)
+}
+
+impl ConstraintSynthesizer<Fr> for JoinSplitCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> ark_relations::r1cs::Result<()> {
+        let (leaf_params_var, two_to_one_params_var) = merkle_parameters_var::<PoseidonMerkle>(cs.clone(), &self.params)?;
+        let commitment_params_var = PoseidonCRHParametersVar::new_constant(
+            ark_relations::ns!(cs, "Commitment Parameters"),
+            &self.commitment,
+        )?;
+
+        // Public inputs, in the order of JoinSplitTransaction::public_inputs
+        let root_var = PoseidonMerkle::root_input(cs.clone(), Some(&self.root))?;
+        let nullifier_vars = self
+            .nullifiers()
+            .iter()
+            .map(|nf| UInt8::new_input_vec(ark_relations::ns!(cs, "nullifier"), nf))
+            .collect::<Result<Vec<_>, _>>()?;
+        let commitment_vars = self
+            .commitments()
+            .iter()
+            .map(|cm| FpVar::new_input(ark_relations::ns!(cs, "commitment"), || Ok(*cm)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let output_rho_vars = self
+            .rhos()
+            .iter()
+            .map(|rho| UInt8::new_input_vec(ark_relations::ns!(cs, "rho"), rho))
+            .collect::<Result<Vec<_>, _>>()?;
+        let vpub_old_var = FpVar::new_input(ark_relations::ns!(cs, "vpub_old"), || Ok(Fr::from(self.vpub_old)))?;
+        let vpub_new_var = FpVar::new_input(ark_relations::ns!(cs, "vpub_new"), || Ok(Fr::from(self.vpub_new)))?;
+        enforce_range_gadget(&vpub_old_var, VALUE_BITS)?;
+        enforce_range_gadget(&vpub_new_var, VALUE_BITS)?;
+
+        let zero_block = UInt8::constant_vec(&[0u8; 32]);
+
+        let mut balance = vpub_old_var;
+        for (input, nf_var) in self.inputs.iter().zip(nullifier_vars.iter()) {
+            let a_sk_var = UInt8::new_witness_vec(ark_relations::ns!(cs, "a_sk"), &input.a_sk)?;
+            let value_var = FpVar::new_witness(ark_relations::ns!(cs, "value"), || Ok(Fr::from(input.note.value)))?;
+            let rho_var = UInt8::new_witness_vec(ark_relations::ns!(cs, "rho"), &input.note.rho)?;
+            let r_var = FpVar::new_witness(ark_relations::ns!(cs, "r"), || Ok(input.note.r))?;
+            let path_var = PathVar::<CommitmentTree, Fr, <PoseidonMerkle as MerkleHash>::PG>::new_witness(
+                ark_relations::ns!(cs, "path"),
+                || Ok(&input.path),
+            )?;
+            enforce_range_gadget(&value_var, VALUE_BITS)?;
+
+            // Ownership: the note is addressed to the key the nullifier is derived from
+            let a_pk_var = blake2s_prf_gadget(&a_sk_var, &zero_block)?;
+            let cm_var = note_commitment_gadget(&commitment_params_var, &a_pk_var, &value_var, &rho_var, &r_var)?;
+
+            // Dummy inputs (value 0) need not be in the tree
+            let is_member = path_var.verify_membership(&leaf_params_var, &two_to_one_params_var, &root_var, &[cm_var])?;
+            is_member.or(&value_var.is_zero()?)?.enforce_equal(&Boolean::TRUE)?;
+
+            let mut top_bits = rho_var[31].to_bits_le()?;
+            top_bits[7].enforce_equal(&Boolean::FALSE)?;
+            top_bits[7] = Boolean::TRUE;
+            let mut nf_input = rho_var[..31].to_vec();
+            nf_input.push(UInt8::from_bits_le(&top_bits));
+            blake2s_prf_gadget(&a_sk_var, &nf_input)?.enforce_equal(nf_var)?;
+
+            balance += value_var;
+        }
+
+        for ((output, cm_var), rho_var) in self.outputs.iter().zip(commitment_vars.iter()).zip(output_rho_vars.iter()) {
+            let a_pk_var = UInt8::new_witness_vec(ark_relations::ns!(cs, "a_pk"), &output.a_pk)?;
+            let value_var = FpVar::new_witness(ark_relations::ns!(cs, "value"), || Ok(Fr::from(output.value)))?;
+            let r_var = FpVar::new_witness(ark_relations::ns!(cs, "r"), || Ok(output.r))?;
+            enforce_range_gadget(&value_var, VALUE_BITS)?;
+            // Spendable later: the top bit of rho is clear
+            rho_var[31].to_bits_le()?[7].enforce_equal(&Boolean::FALSE)?;
+
+            note_commitment_gadget(&commitment_params_var, &a_pk_var, &value_var, rho_var, &r_var)?.enforce_equal(cm_var)?;
+
+            balance -= value_var;
+        }
+
+        // At most 4 terms of 64 bits on each side: no wrap-around in Fr
+        balance.enforce_equal(&vpub_new_var)?;
+
+        Ok(())
+    }
+}
+
+pub fn test_join_split_with_proof() {
+    println!("\n### Running test_join_split_with_proof()...");
+
+    let mut rng = ark_std::rand::rngs::StdRng::seed_from_u64(ark_std::test_rng().next_u64());
+
+    const DEPTH: usize = 16;
+
+    let params = PoseidonMerkle::setup(&mut rng);
+    let (pk, vk) = Groth16::<Bls12_377>::setup(JoinSplitCircuit::blank(params.clone(), DEPTH, &mut rng), &mut rng).unwrap();
+    let mut ledger = Ledger::new(params, DEPTH, vk);
+
+    let mut alice = Wallet::new(&mut rng);
+    let mut bob = Wallet::new(&mut rng);
+
+    // Alice deposits 60 and 40
+    for value in [60, 40] {
+        let note = Note::new(alice.address(), value, &mut rng);
+        let index = ledger.deposit(&note).unwrap();
+        alice.notes.push((note, index));
+    }
+
+    // Alice pays 70 to Bob, 25 back to herself and 5 out of the pool
+    let outputs = vec![Note::new(bob.address(), 70, &mut rng), Note::new(alice.address(), 25, &mut rng)];
+    let circuit = alice.spend(&ledger, &[0, 1], outputs.clone(), 0, 5, &mut rng);
+    let (satisfied, constraints) = is_satisfied(circuit.clone()).unwrap();
+    assert!(satisfied);
+
+    let start = Instant::now();
+    let tx = circuit.prove(&pk, &mut rng);
+    println!("depth {}, {} inputs, {} outputs: {} constraints, prove {} ms", DEPTH, NUM_INPUTS, NUM_OUTPUTS, constraints, start.elapsed().as_millis());
+
+    // Tampered public data does not verify
+    let mut more_out = tx.clone();
+    more_out.vpub_new = 50;
+    assert_eq!(ledger.submit(&more_out), Err(LedgerError::InvalidProof));
+    let mut other_output = tx.clone();
+    other_output.commitments[0] = Note::new(bob.address(), 70, &mut rng).commitment(&ledger.commitment);
+    assert_eq!(ledger.submit(&other_output), Err(LedgerError::InvalidProof));
+
+    let positions = ledger.submit(&tx).unwrap();
+    bob.notes.push((outputs[0].clone(), positions[0]));
+    alice.notes = vec![(outputs[1].clone(), positions[1])];
+    assert_eq!((alice.balance(), bob.balance()), (25, 70));
+
+    // Double spend: the same notes give the same nullifiers, whatever the outputs
+    let alice_again = Wallet {
+        a_sk: alice.a_sk,
+        notes: vec![(circuit.inputs[0].note.clone(), 0), (circuit.inputs[1].note.clone(), 1)],
+    };
+    let replay = alice_again.spend(&ledger, &[0, 1], vec![Note::new(alice.address(), 100, &mut rng), Note::new(alice.address(), 0, &mut rng)], 0, 0, &mut rng);
+    assert!(is_satisfied(replay.clone()).unwrap().0);
+    assert_eq!(ledger.submit(&replay.prove(&pk, &mut rng)), Err(LedgerError::DoubleSpend));
+    assert_eq!(ledger.submit(&tx), Err(LedgerError::DoubleSpend));
+
+    // Bob spends his note with one dummy input: 30 to Alice and 40 to himself
+    let outputs = vec![Note::new(alice.address(), 30, &mut rng), Note::new(bob.address(), 40, &mut rng)];
+    let tx = bob.spend(&ledger, &[0], outputs, 0, 0, &mut rng).prove(&pk, &mut rng);
+    ledger.submit(&tx).unwrap();
+
+    // Inflation: more out than in (values are range-checked, so they cannot wrap around the field)
+    let inflated = alice.spend(&ledger, &[0], vec![Note::new(alice.address(), 26, &mut rng), Note::new(bob.address(), 0, &mut rng)], 0, 0, &mut rng);
+    assert!(!is_satisfied(inflated).unwrap().0);
+
+    // The same note twice in one transaction
+    let twice = Wallet { a_sk: alice.a_sk, notes: vec![alice.notes[0].clone(), alice.notes[0].clone()] };
+    let doubled = twice.spend(&ledger, &[0, 1], vec![Note::new(alice.address(), 50, &mut rng), Note::new(bob.address(), 0, &mut rng)], 0, 0, &mut rng);
+    assert!(is_satisfied(doubled.clone()).unwrap().0);
+    assert_eq!(ledger.submit(&doubled.prove(&pk, &mut rng)), Err(LedgerError::DoubleSpend));
+
+    let balanced = alice.spend(&ledger, &[0], vec![Note::new(alice.address(), 20, &mut rng), Note::new(bob.address(), 0, &mut rng)], 0, 5, &mut rng);
+    assert!(is_satisfied(balanced.clone()).unwrap().0);
+
+    // A non-zero note which is not in the tree, or spent with another key
+    let mut unknown = balanced.clone();
+    unknown.inputs[0].note.r += Fr::from(1u64);
+    assert!(!is_satisfied(unknown).unwrap().0);
+
+    let mut stolen = balanced.clone();
+    stolen.inputs[0].a_sk = bob.a_sk;
+    assert!(!is_satisfied(stolen).unwrap().0);
+
+    // rho and rho | 1 << 255 share a nullifier: a note with the top bit of rho set cannot be
+    // spent, even if it is in the tree, nor created as an output
+    let mut high = Note::new(alice.address(), 10, &mut rng);
+    assert_eq!(high.rho[31] & 0x80, 0);
+    let low_nullifier = nullifier(&alice.a_sk, &high.rho);
+    high.rho[31] |= 0x80;
+    assert_eq!(nullifier(&alice.a_sk, &high.rho), low_nullifier);
+    let high_index = ledger.deposit(&high).unwrap();
+    let holder = Wallet { a_sk: alice.a_sk, notes: vec![(high.clone(), high_index)] };
+    let high_input = holder.spend(&ledger, &[0], vec![Note::new(bob.address(), 10, &mut rng), Note::new(bob.address(), 0, &mut rng)], 0, 0, &mut rng);
+    assert!(!is_satisfied(high_input).unwrap().0);
+    let mut high_output = balanced.clone();
+    high_output.outputs[0].rho[31] |= 0x80;
+    assert!(!is_satisfied(high_output).unwrap().0);
+
+    // The anchor must be a root the ledger has seen
+    let mut unknown_root = balanced.prove(&pk, &mut rng);
+    unknown_root.root += Fr::from(1u64);
+    assert_eq!(ledger.submit(&unknown_root), Err(LedgerError::UnknownRoot));
+
+    // Faerie Gold: two notes to Bob with the same rho have the same nullifier, so he could
+    // spend only one of them. The circuit accepts it, the ledger rejects the repeated rho,
+    // within a transaction, against a note in the tree or in a deposit.
+    let paid = Note::new(bob.address(), 10, &mut rng);
+    let twins = alice.spend(&ledger, &[0], vec![paid.clone(), Note { r: Fr::rand(&mut rng), ..paid.clone() }], 0, 5, &mut rng);
+    assert!(is_satisfied(twins.clone()).unwrap().0);
+    assert_eq!(ledger.submit(&twins.prove(&pk, &mut rng)), Err(LedgerError::RepeatedRho));
+
+    let mut reused = balanced.clone();
+    reused.outputs[1].rho = alice.notes[0].0.rho;
+    assert!(is_satisfied(reused.clone()).unwrap().0);
+    assert_eq!(ledger.submit(&reused.prove(&pk, &mut rng)), Err(LedgerError::RepeatedRho));
+    assert_eq!(ledger.deposit(&Note { r: Fr::rand(&mut rng), ..alice.notes[0].0.clone() }), Err(LedgerError::RepeatedRho));
+
+    // A transaction rejected because the tree is full does not burn its input notes
+    let free = ledger.state.leaves.len() - ledger.next_index;
+    ledger.append(&vec![Fr::zero(); free - 1]).unwrap();
+    let spent = ledger.nullifiers.clone();
+    assert_eq!(ledger.submit(&balanced.prove(&pk, &mut rng)), Err(LedgerError::TreeFull));
+    assert_eq!(ledger.nullifiers, spent);
+    assert_eq!(ledger.next_index, ledger.state.leaves.len() - 1);
+}
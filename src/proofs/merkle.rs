@@ -0,0 +1,156 @@
+use std::time::Instant;
+
+use ark_relations::r1cs::{ConstraintSystem, ConstraintSystemRef, SynthesisError, ConstraintSynthesizer};
+use ark_bls12_377::{
+    Fr,
+    Bls12_377,
+};
+use ark_std::rand::{SeedableRng, RngCore};
+use ark_snark::{
+    CircuitSpecificSetupSNARK,
+    SNARK,
+};
+use ark_r1cs_std::alloc::AllocVar;
+use ark_crypto_primitives::merkle_tree::{
+    constraints::PathVar,
+    MerkleTree,
+    Path,
+};
+
+use crate::gadgets::{
+    MerkleHash,
+    MerkleParameters,
+    Sha256Merkle,
+    PedersenMerkle,
+    PoseidonMerkle,
+    merkle_parameters_var,
+    enforce_membership,
+    random_path,
+    MerkleRoot,
+};
+
+// Membership of a leaf in a Merkle tree.
+// The root is the public input; the leaf and its authentication path are witnesses.
+pub struct MerkleMembershipCircuit<H: MerkleHash> {
+    pub params: MerkleParameters<H::P>,
+    pub root: Option<MerkleRoot<H>>,
+    pub leaf: Option<Vec<H::LeafElement>>,
+    pub path: Option<Path<H::P>>,
+}
+
+impl<H: MerkleHash> Clone for MerkleMembershipCircuit<H> {
+    fn clone(&self) -> Self {
+        MerkleMembershipCircuit {
+            params: self.params.clone(),
+            root: self.root.clone(),
+            leaf: self.leaf.clone(),
+            path: self.path.clone(),
+        }
+    }
+}
+
+impl<H: MerkleHash> MerkleMembershipCircuit<H> {
+    pub fn new(
+        params: MerkleParameters<H::P>,
+        root: MerkleRoot<H>,
+        leaf: Vec<H::LeafElement>,
+        path: Path<H::P>,
+    ) -> Self {
+        MerkleMembershipCircuit {
+            params,
+            root: Some(root),
+            leaf: Some(leaf),
+            path: Some(path),
+        }
+    }
+
+    // Placeholder circuit for setup: a random path of the right depth.
+    // NOTE: The leaf length is fixed by the circuit (32 bytes here)
+    pub fn blank<R: RngCore>(params: MerkleParameters<H::P>, depth: usize, rng: &mut R) -> Self {
+        let (leaf, path, root) = random_path::<H, _>(&params, depth, rng);
+        MerkleMembershipCircuit::new(params, root, leaf, path)
+    }
+
+    pub fn public_inputs(&self) -> Vec<Fr> {
+        H::root_to_field_elements(self.root.as_ref().unwrap())
+    }
+}
+
+impl<H: MerkleHash> ConstraintSynthesizer<Fr> for MerkleMembershipCircuit<H> {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> ark_relations::r1cs::Result<()> {
+        let params_var = merkle_parameters_var::<H>(cs.clone(), &self.params)?;
+        let root_var = H::root_input(cs.clone(), self.root.as_ref())?;
+        let leaf_var = Vec::<H::LeafElementVar>::new_witness(
+            ark_relations::ns!(cs, "leaf"),
+            || self.leaf.as_deref().ok_or(SynthesisError::AssignmentMissing),
+        )?;
+        let path_var = PathVar::<H::P, Fr, H::PG>::new_witness(
+            ark_relations::ns!(cs, "path"),
+            || self.path.as_ref().ok_or(SynthesisError::AssignmentMissing),
+        )?;
+
+        enforce_membership::<H>(&params_var, &root_var, &leaf_var, &path_var)?;
+
+        Ok(())
+    }
+}
+
+// Proves membership of one leaf of a tree of 2^depth 32-byte leaves, then checks that
+// the proof does not verify against another root and that a wrong leaf is not accepted
+fn prove_membership<H: MerkleHash>(depth: usize) {
+    use ark_groth16::Groth16;
+
+    let mut rng = ark_std::rand::rngs::StdRng::seed_from_u64(ark_std::test_rng().next_u64());
+
+    let params = H::setup(&mut rng);
+    let (pk, vk) = Groth16::<Bls12_377>::setup(MerkleMembershipCircuit::<H>::blank(params.clone(), depth, &mut rng), &mut rng).unwrap();
+
+    let leaves: Vec<Vec<H::LeafElement>> = (0..1u32 << depth)
+        .map(|i| {
+            let mut bytes = [0u8; 32];
+            bytes[..4].copy_from_slice(&i.to_le_bytes());
+            H::leaf(&bytes)
+        })
+        .collect();
+    let mut tree = MerkleTree::<H::P>::new(&params.leaf, &params.two_to_one, leaves.iter().map(|leaf| leaf.as_slice())).unwrap();
+
+    let index = (rng.next_u32() as usize) & ((1 << depth) - 1);
+    let circuit = MerkleMembershipCircuit::<H>::new(
+        params.clone(),
+        tree.root(),
+        leaves[index].clone(),
+        tree.generate_proof(index).unwrap(),
+    );
+
+    let start = Instant::now();
+    let proof = Groth16::<Bls12_377>::prove(&pk, circuit.clone(), &mut rng).unwrap();
+    let prove_time = start.elapsed();
+    assert!(Groth16::<Bls12_377>::verify(&vk, circuit.public_inputs().as_slice(), &proof).unwrap());
+
+    // Another leaf changes the root: the old proof does not verify against it
+    let other = (index + 1) & ((1 << depth) - 1);
+    tree.update(other, leaves[index].as_slice()).unwrap();
+    let updated_root = H::root_to_field_elements(&tree.root());
+    assert!(!Groth16::<Bls12_377>::verify(&vk, updated_root.as_slice(), &proof).unwrap());
+
+    // A leaf which is not at this position does not satisfy the circuit
+    let wrong_circuit = MerkleMembershipCircuit::<H> {
+        leaf: Some(leaves[other].clone()),
+        ..circuit.clone()
+    };
+    let cs = ConstraintSystem::<Fr>::new_ref();
+    wrong_circuit.generate_constraints(cs.clone()).unwrap();
+    assert!(!cs.is_satisfied().unwrap());
+
+    println!("{:>10} | {:>6} | {:>12} | {:>10}", H::NAME, depth, pk.vk.gamma_abc_g1.len() - 1, prove_time.as_millis());
+}
+
+pub fn test_merkle_membership_with_proof() {
+    println!("\n### Running test_merkle_membership_with_proof()...");
+
+    println!("{:>10} | {:>6} | {:>12} | {:>10}", "hash", "depth", "public", "prove (ms)");
+    prove_membership::<Sha256Merkle>(8);
+    prove_membership::<PedersenMerkle>(8);
+    prove_membership::<PoseidonMerkle>(8);
+    prove_membership::<PoseidonMerkle>(16);
+}
@@ -0,0 +1,284 @@
+use std::time::Instant;
+
+use ark_relations::r1cs::{ConstraintSystemRef, SynthesisError, ConstraintSynthesizer};
+use ark_bls12_377::{
+    Fr,
+    Bls12_377,
+};
+use ark_std::rand::{SeedableRng, RngCore};
+use ark_snark::{
+    CircuitSpecificSetupSNARK,
+    SNARK,
+};
+use ark_r1cs_std::{
+    alloc::AllocVar,
+    eq::EqGadget,
+};
+use ark_crypto_primitives::merkle_tree::{
+    constraints::PathVar,
+    MerkleTree,
+    Path,
+};
+
+use crate::gadgets::{
+    MerkleHash,
+    MerkleParameters,
+    PoseidonMerkle,
+    Sha256Merkle,
+    PedersenMerkle,
+    merkle_parameters_var,
+    random_path,
+    MerkleRoot,
+};
+use crate::utils::is_satisfied;
+
+// Batched Merkle root updates, as in a rollup: applying K leaf updates in order moves the
+// tree from root R0 to root R1.
+
+// One leaf update, with the path of the leaf in the tree it is applied to
+pub struct LeafUpdate<H: MerkleHash> {
+    pub index: usize,
+    pub old_leaf: Vec<H::LeafElement>,
+    pub new_leaf: Vec<H::LeafElement>,
+    pub path: Path<H::P>,
+}
+
+impl<H: MerkleHash> Clone for LeafUpdate<H> {
+    fn clone(&self) -> Self {
+        LeafUpdate {
+            index: self.index,
+            old_leaf: self.old_leaf.clone(),
+            new_leaf: self.new_leaf.clone(),
+            path: self.path.clone(),
+        }
+    }
+}
+
+// Native state: the tree and its leaves, producing the witnesses of each batch
+pub struct MerkleState<H: MerkleHash> {
+    pub params: MerkleParameters<H::P>,
+    pub depth: usize,
+    pub tree: MerkleTree<H::P>,
+    pub leaves: Vec<Vec<H::LeafElement>>,
+}
+
+impl<H: MerkleHash> MerkleState<H> {
+    pub fn new(params: MerkleParameters<H::P>, leaves: Vec<Vec<H::LeafElement>>) -> Self {
+        assert!(leaves.len().is_power_of_two() && leaves.len() > 1);
+        let depth = leaves.len().trailing_zeros() as usize;
+        let tree = MerkleTree::<H::P>::new(&params.leaf, &params.two_to_one, leaves.iter().map(|leaf| leaf.as_slice())).unwrap();
+        MerkleState { params, depth, tree, leaves }
+    }
+
+    pub fn root(&self) -> MerkleRoot<H> {
+        self.tree.root()
+    }
+
+    // Applies the updates in order; an index may appear several times
+    pub fn apply(&mut self, updates: &[(usize, Vec<H::LeafElement>)]) -> Vec<LeafUpdate<H>> {
+        updates
+            .iter()
+            .map(|(index, new_leaf)| {
+                let update = LeafUpdate {
+                    index: *index,
+                    old_leaf: self.leaves[*index].clone(),
+                    new_leaf: new_leaf.clone(),
+                    path: self.tree.generate_proof(*index).unwrap(),
+                };
+                self.tree.update(*index, new_leaf.as_slice()).unwrap();
+                self.leaves[*index] = new_leaf.clone();
+                update
+            })
+            .collect()
+    }
+}
+
+// R0 and R1 are public inputs; the leaves and paths of the K updates are witnesses.
+// Each path is checked against the root left by the previous update, and the last root must be R1.
+pub struct MerkleUpdateCircuit<H: MerkleHash> {
+    pub params: MerkleParameters<H::P>,
+    pub old_root: MerkleRoot<H>,
+    pub new_root: MerkleRoot<H>,
+    pub updates: Vec<LeafUpdate<H>>,
+}
+
+impl<H: MerkleHash> Clone for MerkleUpdateCircuit<H> {
+    fn clone(&self) -> Self {
+        MerkleUpdateCircuit {
+            params: self.params.clone(),
+            old_root: self.old_root.clone(),
+            new_root: self.new_root.clone(),
+            updates: self.updates.clone(),
+        }
+    }
+}
+
+impl<H: MerkleHash> MerkleUpdateCircuit<H> {
+    // Applies `updates` to `state` and returns the circuit of the transition
+    pub fn apply(state: &mut MerkleState<H>, updates: &[(usize, Vec<H::LeafElement>)]) -> Self {
+        let old_root = state.root();
+        let updates = state.apply(updates);
+        MerkleUpdateCircuit {
+            params: state.params.clone(),
+            old_root,
+            new_root: state.root(),
+            updates,
+        }
+    }
+
+    // Placeholder circuit for setup: K random paths of the right depth
+    pub fn blank<R: RngCore>(params: MerkleParameters<H::P>, depth: usize, batch_size: usize, rng: &mut R) -> Self {
+        let updates: Vec<LeafUpdate<H>> = (0..batch_size)
+            .map(|_| {
+                let (leaf, path, _) = random_path::<H, _>(&params, depth, rng);
+                LeafUpdate { index: path.leaf_index, old_leaf: leaf.clone(), new_leaf: leaf, path }
+            })
+            .collect();
+        let (_, _, root) = random_path::<H, _>(&params, depth, rng);
+        MerkleUpdateCircuit { params, old_root: root.clone(), new_root: root, updates }
+    }
+
+    pub fn public_inputs(&self) -> Vec<Fr> {
+        let mut inputs = H::root_to_field_elements(&self.old_root);
+        inputs.extend(H::root_to_field_elements(&self.new_root));
+        inputs
+    }
+}
+
+impl<H: MerkleHash> ConstraintSynthesizer<Fr> for MerkleUpdateCircuit<H> {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> ark_relations::r1cs::Result<()> {
+        let (leaf_params_var, two_to_one_params_var) = merkle_parameters_var::<H>(cs.clone(), &self.params)?;
+        let old_root_var = H::root_input(cs.clone(), Some(&self.old_root))?;
+        let new_root_var = H::root_input(cs.clone(), Some(&self.new_root))?;
+
+        let mut root_var = old_root_var;
+        for update in self.updates.iter() {
+            let old_leaf_var = Vec::<H::LeafElementVar>::new_witness(
+                ark_relations::ns!(cs, "old_leaf"),
+                || Ok(update.old_leaf.as_slice()),
+            )?;
+            let new_leaf_var = Vec::<H::LeafElementVar>::new_witness(
+                ark_relations::ns!(cs, "new_leaf"),
+                || Ok(update.new_leaf.as_slice()),
+            )?;
+            let path_var = PathVar::<H::P, Fr, H::PG>::new_witness(ark_relations::ns!(cs, "path"), || Ok(&update.path))?;
+
+            // The same path (and so the same position) for the old and the new leaf
+            root_var = path_var.update_leaf(&leaf_params_var, &two_to_one_params_var, &root_var, &old_leaf_var, &new_leaf_var)?;
+        }
+        root_var.enforce_equal(&new_root_var)?;
+
+        Ok(())
+    }
+}
+
+fn numbered_leaf<H: MerkleHash>(n: u64) -> Vec<H::LeafElement> {
+    let mut bytes = [0u8; 32];
+    bytes[..8].copy_from_slice(&n.to_le_bytes());
+    H::leaf(&bytes)
+}
+
+fn random_updates<H: MerkleHash, R: RngCore>(depth: usize, batch_size: usize, rng: &mut R) -> Vec<(usize, Vec<H::LeafElement>)> {
+    (0..batch_size)
+        .map(|_| ((rng.next_u32() as usize) & ((1 << depth) - 1), numbered_leaf::<H>(rng.next_u64())))
+        .collect()
+}
+
+// Checks a batch natively and in-circuit (including an index updated twice) and that
+// reordered or tampered batches are rejected; returns the constraints of a batch of `batch_size`
+fn check_merkle_update<H: MerkleHash>(depth: usize, batch_size: usize) -> Result<usize, SynthesisError> {
+    let mut rng = ark_std::rand::rngs::StdRng::seed_from_u64(ark_std::test_rng().next_u64());
+
+    let params = H::setup(&mut rng);
+    let mut state = MerkleState::<H>::new(params, (0..1u64 << depth).map(numbered_leaf::<H>).collect());
+
+    let mut updates = random_updates::<H, _>(depth, batch_size, &mut rng);
+    // The same leaf twice, then its sibling
+    updates[1].0 = updates[0].0;
+    updates[batch_size - 1].0 = updates[0].0 ^ 1;
+    let circuit = MerkleUpdateCircuit::apply(&mut state, &updates);
+
+    // Rebuilding the final leaves gives the same root
+    let rebuilt = MerkleState::<H>::new(state.params.clone(), state.leaves.clone());
+    assert!(rebuilt.root() == circuit.new_root);
+
+    let (satisfied, constraints) = is_satisfied(circuit.clone())?;
+    assert!(satisfied);
+
+    // Another final root
+    let mut wrong_root = circuit.clone();
+    wrong_root.new_root = wrong_root.old_root.clone();
+    assert!(!is_satisfied(wrong_root)?.0);
+
+    // A new leaf different from the one the root commits to
+    let mut wrong_leaf = circuit.clone();
+    wrong_leaf.updates[0].new_leaf = numbered_leaf::<H>(u64::MAX);
+    assert!(!is_satisfied(wrong_leaf)?.0);
+
+    // A path taken in R0 instead of the intermediate root
+    let mut stale_path = circuit.clone();
+    let stale = MerkleState::<H>::new(state.params.clone(), (0..1u64 << depth).map(numbered_leaf::<H>).collect());
+    stale_path.updates[batch_size - 1].path = stale.tree.generate_proof(updates[batch_size - 1].0).unwrap();
+    assert!(!is_satisfied(stale_path)?.0);
+
+    Ok(constraints)
+}
+
+pub fn test_merkle_update_with_proof() {
+    println!("\n### Running test_merkle_update_with_proof()...");
+
+    use ark_groth16::Groth16;
+
+    for batch_size in [3, 4] {
+        check_merkle_update::<Sha256Merkle>(4, batch_size).unwrap();
+        check_merkle_update::<PedersenMerkle>(4, batch_size).unwrap();
+        check_merkle_update::<PoseidonMerkle>(4, batch_size).unwrap();
+    }
+
+    let mut rng = ark_std::rand::rngs::StdRng::seed_from_u64(ark_std::test_rng().next_u64());
+
+    const DEPTH: usize = 16;
+
+    let params = PoseidonMerkle::setup(&mut rng);
+    let mut state = MerkleState::<PoseidonMerkle>::new(
+        params.clone(),
+        (0..1u64 << DEPTH).map(numbered_leaf::<PoseidonMerkle>).collect(),
+    );
+
+    println!("poseidon, depth {}", DEPTH);
+    println!("{:>4} | {:>12} | {:>10} | {:>10} | {:>11}", "K", "constraints", "setup (ms)", "prove (ms)", "verify (ms)");
+    for batch_size in [1, 8, 32] {
+        let start = Instant::now();
+        let (pk, vk) = Groth16::<Bls12_377>::setup(
+            MerkleUpdateCircuit::<PoseidonMerkle>::blank(params.clone(), DEPTH, batch_size, &mut rng),
+            &mut rng,
+        ).unwrap();
+        let setup_time = start.elapsed();
+
+        let updates = random_updates::<PoseidonMerkle, _>(DEPTH, batch_size, &mut rng);
+        let circuit = MerkleUpdateCircuit::apply(&mut state, &updates);
+        let (_, constraints) = is_satisfied(circuit.clone()).unwrap();
+
+        let start = Instant::now();
+        let proof = Groth16::<Bls12_377>::prove(&pk, circuit.clone(), &mut rng).unwrap();
+        let prove_time = start.elapsed();
+
+        let start = Instant::now();
+        assert!(Groth16::<Bls12_377>::verify(&vk, circuit.public_inputs().as_slice(), &proof).unwrap());
+        let verify_time = start.elapsed();
+
+        // Swapped roots: the reverse transition is not proven
+        let mut reversed = circuit.public_inputs();
+        reversed.swap(0, 1);
+        assert!(!Groth16::<Bls12_377>::verify(&vk, reversed.as_slice(), &proof).unwrap());
+
+        println!(
+            "{:>4} | {:>12} | {:>10} | {:>10} | {:>11}",
+            batch_size,
+            constraints,
+            setup_time.as_millis(),
+            prove_time.as_millis(),
+            verify_time.as_millis(),
+        );
+    }
+}
@@ -1,7 +1,31 @@
 mod pairing;
 mod fp;
 mod crh;
+mod bls;
+mod bls_committee;
+mod hash_chain;
+mod disclosure;
+mod commitment;
+mod merkle;
+mod sparse_merkle;
+mod merkle_update;
+mod rollup;
+mod join_split;
+mod signature;
+mod eddsa;
 
-pub use pairing::test_pairing_with_proof;
+pub use pairing::{test_pairing_with_proof, test_pairing_fixed_g2_with_proof, test_pairing_digest_with_proof};
 pub use fp::test_fp_with_proof;
-pub use crh::test_sha256_crh_with_proof;
\ No newline at end of file
+pub use crh::{test_sha256_crh_with_proof, test_blake2s_crh_with_proof, test_pedersen_crh_with_proof, test_poseidon_crh_with_proof, test_mimc_crh_with_proof, test_crh_cost_matrix};
+pub use bls::test_bls_signature_with_proof;
+pub use bls_committee::{test_bls_committee_costs, test_bls_committee_with_proof};
+pub use hash_chain::test_sha256_chain_with_proof;
+pub use disclosure::test_sha256_disclosure_with_proof;
+pub use commitment::test_pedersen_commitment_with_proof;
+pub use merkle::test_merkle_membership_with_proof;
+pub use sparse_merkle::test_sparse_merkle_with_proof;
+pub use merkle_update::test_merkle_update_with_proof;
+pub use rollup::test_rollup_with_proof;
+pub use join_split::test_join_split_with_proof;
+pub use signature::test_schnorr_signature_with_proof;
+pub use eddsa::test_eddsa_batch_with_proof;
\ No newline at end of file
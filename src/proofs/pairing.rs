@@ -23,12 +23,20 @@ use ark_r1cs_std::{
     eq::EqGadget,
     ToBytesGadget,
     pairing::PairingVar, 
-    R1CSVar
+    R1CSVar,
+    fields::fp::FpVar,
+    ToConstraintFieldGadget,
 };
 use ark_ec::pairing::{
     Pairing, // To call "native" pairing()
     PairingOutput,
 };
+use ark_ec::bls12::G2Prepared;
+use ark_crypto_primitives::{
+    crh::{poseidon, CRHScheme, CRHSchemeGadget},
+    sponge::poseidon::PoseidonConfig,
+};
+use ark_serialize::{CanonicalSerialize, Compress};
 
 use ark_ec::CurveGroup; // To use into_affine()
 use ark_snark::{
@@ -36,6 +44,8 @@ use ark_snark::{
     SNARK,
 };
 
+use crate::utils::poseidon::poseidon_parameters;
+
 type Bls12G1 = <Bls12_377 as Pairing>::G1;
 type Bls12G2 = <Bls12_377 as Pairing>::G2;
 type Bls12Target = <Bls12_377 as Pairing>::TargetField;
@@ -43,6 +53,114 @@ type Bls12G1Var = ark_bls12_377::constraints::G1Var;
 type Bls12G2Var = ark_bls12_377::constraints::G2Var;
 type Bls12TargetVar = ark_bls12_377::constraints::Fq12Var;
 type Bls12PairingVar = ark_bls12_377::constraints::PairingVar;
+type Bls12G2PreparedVar = ark_r1cs_std::groups::bls12::G2PreparedVar<Config>;
+type PoseidonCRH = poseidon::CRH<Fr_bw>;
+type PoseidonCRHGadget = poseidon::constraints::CRHGadget<Fr_bw>;
+type PoseidonCRHParametersVar = poseidon::constraints::CRHParametersVar<Fr_bw>;
+
+#[derive(Clone, Debug)]
+pub struct PairingCircuit {
+    pub element_g1: Option<Bls12G1>,
+    pub element_g2: Option<Bls12G2>,
+    //element_gt: Bls12Target,
+    pub element_gt: PairingOutput<Bls12_377>,
+    // G2 is a circuit constant, prepared natively at setup time
+    pub fixed_g2: bool,
+}
+
+impl Default for PairingCircuit {
+    fn default() -> PairingCircuit {
+        let mut rng = ark_std::test_rng();
+        let eg1 = Bls12G1::rand(&mut rng);
+        let eg2 = Bls12G2::rand(&mut rng);
+        let pc = PairingCircuit {
+            // NOTE: The input length must be fixed: fix real input or apply padding
+            element_g1: Some(eg1),
+            element_g2: Some(eg2),
+            element_gt: <Bls12_377 as Pairing>::pairing(eg1, eg2),
+            fixed_g2: false,
+        };
+        pc
+    }
+}
+
+impl PairingCircuit {
+    // e(element_g1, element_g2) computed in-circuit
+    fn pairing_var(&self, cs: ConstraintSystemRef<Fr_bw>) -> Result<Bls12TargetVar, SynthesisError> {
+        let a_var = Bls12G1Var::new_witness(
+            ark_relations::ns!(cs, "a"), 
+            || Ok(self.element_g1.unwrap().into_affine())
+        )?;            
+
+        let a_var_prep = Bls12PairingVar::prepare_g1(&a_var)?;
+        let b_var_prep = if self.fixed_g2 {
+            Bls12G2PreparedVar::new_constant(
+                ark_relations::ns!(cs, "b_prepared"),
+                G2Prepared::from(self.element_g2.unwrap()),
+            )?
+        } else {
+            let b_var = Bls12G2Var::new_witness(
+                ark_relations::ns!(cs, "b"), 
+                || Ok(self.element_g2.unwrap().into_affine())
+            )?;
+            Bls12PairingVar::prepare_g2(&b_var)?
+        };
+        Bls12PairingVar::pairing(a_var_prep, b_var_prep)
+    }
+}
+
+impl ConstraintSynthesizer<Fr_bw> for PairingCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr_bw>) -> ark_relations::r1cs::Result<()> {
+
+        // pub type Fp12Var<P> = QuadExtVar<Fp6Var<<P as Fp12Config>::Fp6Config>, Fp12ConfigWrapper<P>>;
+        let pairing_native_var = Bls12TargetVar::new_input(
+            ark_relations::ns!(cs, "c"),
+            || Ok(self.element_gt.0)
+        );
+
+        let pairing_var = self.pairing_var(cs)?;
+
+        pairing_var.enforce_equal(&pairing_native_var?)?;
+
+        Ok(())
+    }
+}
+
+// Same statement as PairingCircuit, but the 12 Fq coefficients of the target element are hashed
+// in-circuit with Poseidon and only the digest is a public input
+#[derive(Clone)]
+pub struct PairingDigestCircuit {
+    pub circuit: PairingCircuit,
+    pub poseidon_params: PoseidonConfig<Fr_bw>,
+}
+
+impl PairingDigestCircuit {
+    pub fn digest(&self) -> Fr_bw {
+        let coefficients: Vec<_> = self.circuit.element_gt.0.to_base_prime_field_elements().collect();
+        PoseidonCRH::evaluate(&self.poseidon_params, coefficients.as_slice()).unwrap()
+    }
+}
+
+impl ConstraintSynthesizer<Fr_bw> for PairingDigestCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr_bw>) -> ark_relations::r1cs::Result<()> {
+        let digest_var = FpVar::new_input(
+            ark_relations::ns!(cs, "digest"),
+            || Ok(self.digest()),
+        )?;
+        let params_var = PoseidonCRHParametersVar::new_constant(
+            ark_relations::ns!(cs, "poseidon_params"),
+            &self.poseidon_params,
+        )?;
+
+        let pairing_var = self.circuit.pairing_var(cs)?;
+        let coefficients = pairing_var.to_constraint_field()?;
+        let hash_var = PoseidonCRHGadget::evaluate(&params_var, coefficients.as_slice())?;
+
+        hash_var.enforce_equal(&digest_var)?;
+
+        Ok(())
+    }
+}
 
 pub fn test_pairing_with_proof() {
     println!("\n### Running test_pairing_with_proof()...");
@@ -55,67 +173,161 @@ pub fn test_pairing_with_proof() {
     //let mut rng = ark_std::test_rng();
     let mut rng = ark_std::rand::rngs::StdRng::seed_from_u64(ark_std::test_rng().next_u64());
 
-    #[derive(Clone, Debug)]
-    struct PairingCircuit {
-        element_g1: Option<Bls12G1>,
-        element_g2: Option<Bls12G2>,
-        //element_gt: Bls12Target,
-        element_gt: PairingOutput<Bls12_377>,
-    }
+    let circuit_default = PairingCircuit::default();
 
-    impl Default for PairingCircuit {
-        fn default() -> PairingCircuit {
-            let mut rng = ark_std::test_rng();
-            let eg1 = Bls12G1::rand(&mut rng);
-            let eg2 = Bls12G2::rand(&mut rng);
-            let pc = PairingCircuit {
-                // NOTE: The input length must be fixed: fix real input or apply padding
-                element_g1: Some(eg1),
-                element_g2: Some(eg2),
-                element_gt: <Bls12_377 as Pairing>::pairing(eg1, eg2),
-            };
-            pc
-        }
-    }
+    let (pk, vk) = Groth16::<BW6_761>::setup(circuit_default, &mut rng).unwrap();
 
-    impl ConstraintSynthesizer<Fr_bw> for PairingCircuit {
-        fn generate_constraints(self, cs: ConstraintSystemRef<Fr_bw>) -> ark_relations::r1cs::Result<()> {
+    let circuit = PairingCircuit::default();
 
-            let a_var = Bls12G1Var::new_witness(
-                ark_relations::ns!(cs, "a"), 
-                || Ok(self.element_g1.unwrap().into_affine())
-            )?;            
-            let b_var = Bls12G2Var::new_witness(
-                ark_relations::ns!(cs, "b"), 
-                || Ok(self.element_g2.unwrap().into_affine())
-            )?;
-            // pub type Fp12Var<P> = QuadExtVar<Fp6Var<<P as Fp12Config>::Fp6Config>, Fp12ConfigWrapper<P>>;
-            let pairing_native_var = Bls12TargetVar::new_input(
-                ark_relations::ns!(cs, "c"),
-                || Ok(self.element_gt.0)
-            );
+    let proof = Groth16::<BW6_761>::prove(&pk, circuit.clone(), &mut rng).unwrap();
+
+    let public_input: Vec<_> = circuit.element_gt.0.to_base_prime_field_elements().map(|x| x).collect();
+
+    assert!(Groth16::<BW6_761>::verify(&vk, public_input.as_slice(), &proof).unwrap());
+
+
+}
+
+// Pairing of a variable G1 with a fixed G2, against the all-witness PairingCircuit
+pub fn test_pairing_fixed_g2_with_proof() {
+    println!("\n### Running test_pairing_fixed_g2_with_proof()...");
+
+    use ark_groth16::Groth16;
+    use std::time::Instant;
+
+    let mut rng = ark_std::rand::rngs::StdRng::seed_from_u64(ark_std::test_rng().next_u64());
+
+    // The fixed G2 element is the one baked into the default circuit
+    let fixed_g2 = PairingCircuit::default().element_g2.unwrap();
+
+    println!("{:>10} | {:>12} | {:>12} | {:>10} | {:>10}", "G2", "constraints", "witness", "setup", "prove");
+    for is_fixed in [false, true] {
+        let circuit_default = PairingCircuit {
+            fixed_g2: is_fixed,
+            ..PairingCircuit::default()
+        };
+
+        let cs = ConstraintSystem::<Fr_bw>::new_ref();
+        circuit_default.clone().generate_constraints(cs.clone()).unwrap();
+        assert!(cs.is_satisfied().unwrap());
+        cs.finalize();
+
+        let start = Instant::now();
+        let (pk, vk) = Groth16::<BW6_761>::setup(circuit_default, &mut rng).unwrap();
+        let setup_time = start.elapsed();
 
-            let a_var_prep = Bls12PairingVar::prepare_g1(&a_var)?;
-            let b_var_prep = Bls12PairingVar::prepare_g2(&b_var)?;
-            let pairing_var = Bls12PairingVar::pairing(a_var_prep, b_var_prep)?;
+        let element_g1 = Bls12G1::rand(&mut rng);
+        let circuit = PairingCircuit {
+            element_g1: Some(element_g1),
+            element_g2: Some(fixed_g2),
+            element_gt: <Bls12_377 as Pairing>::pairing(element_g1, fixed_g2),
+            fixed_g2: is_fixed,
+        };
 
-            pairing_var.enforce_equal(&pairing_native_var.unwrap());
+        let start = Instant::now();
+        let proof = Groth16::<BW6_761>::prove(&pk, circuit.clone(), &mut rng).unwrap();
+        let prove_time = start.elapsed();
 
-            Ok(())
-        }
+        let public_input: Vec<_> = circuit.element_gt.0.to_base_prime_field_elements().collect();
+        assert!(Groth16::<BW6_761>::verify(&vk, public_input.as_slice(), &proof).unwrap());
+
+        // The proof does not verify for the pairing with another G2 element
+        let other_gt = <Bls12_377 as Pairing>::pairing(element_g1, Bls12G2::rand(&mut rng));
+        let other_input: Vec<_> = other_gt.0.to_base_prime_field_elements().collect();
+        assert!(!Groth16::<BW6_761>::verify(&vk, other_input.as_slice(), &proof).unwrap());
+
+        println!(
+            "{:>10} | {:>12} | {:>12} | {:>10.2?} | {:>10.2?}",
+            if is_fixed { "constant" } else { "witness" },
+            cs.num_constraints(),
+            cs.num_witness_variables(),
+            setup_time,
+            prove_time,
+        );
     }
+}
 
-    let circuit_default = PairingCircuit::default();
 
-    let (pk, vk) = Groth16::<BW6_761>::setup(circuit_default, &mut rng).unwrap();
+// Public input layout of test_pairing_with_proof (12 Fq coefficients) against a single Poseidon digest
+pub fn test_pairing_digest_with_proof() {
+    println!("\n### Running test_pairing_digest_with_proof()...");
 
-    let circuit = PairingCircuit::default();
+    use ark_groth16::Groth16;
+    use std::time::Instant;
+
+    const VERIFICATIONS: u32 = 20;
+
+    let mut rng = ark_std::rand::rngs::StdRng::seed_from_u64(ark_std::test_rng().next_u64());
 
+    let poseidon_params = poseidon_parameters::<Fr_bw>(2);
+
+    // Plain layout
+    let circuit = PairingCircuit::default();
+    let (pk, vk) = Groth16::<BW6_761>::setup(circuit.clone(), &mut rng).unwrap();
     let proof = Groth16::<BW6_761>::prove(&pk, circuit.clone(), &mut rng).unwrap();
+    let public_input: Vec<_> = circuit.element_gt.0.to_base_prime_field_elements().collect();
 
-    let public_input: Vec<_> = circuit.element_gt.0.to_base_prime_field_elements().map(|x| x).collect();
+    let pvk = Groth16::<BW6_761>::process_vk(&vk).unwrap();
+    let start = Instant::now();
+    for _ in 0..VERIFICATIONS {
+        assert!(Groth16::<BW6_761>::verify_with_processed_vk(&pvk, public_input.as_slice(), &proof).unwrap());
+    }
+    let verify_time = start.elapsed() / VERIFICATIONS;
 
-    assert!(Groth16::<BW6_761>::verify(&vk, public_input.as_slice(), &proof).unwrap());
+    // Digest layout
+    let digest_circuit = PairingDigestCircuit {
+        circuit: PairingCircuit::default(),
+        poseidon_params: poseidon_params.clone(),
+    };
+    let (digest_pk, digest_vk) = Groth16::<BW6_761>::setup(digest_circuit.clone(), &mut rng).unwrap();
+    let digest_proof = Groth16::<BW6_761>::prove(&digest_pk, digest_circuit.clone(), &mut rng).unwrap();
+    let digest_input = [digest_circuit.digest()];
+
+    let digest_pvk = Groth16::<BW6_761>::process_vk(&digest_vk).unwrap();
+    let start = Instant::now();
+    for _ in 0..VERIFICATIONS {
+        assert!(Groth16::<BW6_761>::verify_with_processed_vk(&digest_pvk, &digest_input, &digest_proof).unwrap());
+    }
+    let digest_verify_time = start.elapsed() / VERIFICATIONS;
+
+    // A digest of another target element is rejected
+    let other_circuit = PairingDigestCircuit {
+        circuit: PairingCircuit {
+            element_gt: <Bls12_377 as Pairing>::pairing(Bls12G1::rand(&mut rng), Bls12G2::rand(&mut rng)),
+            ..PairingCircuit::default()
+        },
+        poseidon_params: poseidon_params.clone(),
+    };
+    assert!(!Groth16::<BW6_761>::verify(&digest_vk, &[other_circuit.digest()], &digest_proof).unwrap());
 
+    let constraints = |c: &dyn Fn(ConstraintSystemRef<Fr_bw>)| {
+        let cs = ConstraintSystem::<Fr_bw>::new_ref();
+        c(cs.clone());
+        assert!(cs.is_satisfied().unwrap());
+        cs.finalize();
+        cs.num_constraints()
+    };
+    let plain_constraints = constraints(&|cs| circuit.clone().generate_constraints(cs).unwrap());
+    let digest_constraints = constraints(&|cs| digest_circuit.clone().generate_constraints(cs).unwrap());
 
+    println!(
+        "{:>8} | {:>13} | {:>12} | {:>14} | {:>12}",
+        "layout", "public inputs", "constraints", "vk size (B)", "verify",
+    );
+    println!(
+        "{:>8} | {:>13} | {:>12} | {:>14} | {:>12.2?}",
+        "fq12",
+        public_input.len(),
+        plain_constraints,
+        vk.serialized_size(Compress::Yes),
+        verify_time,
+    );
+    println!(
+        "{:>8} | {:>13} | {:>12} | {:>14} | {:>12.2?}",
+        "digest",
+        digest_input.len(),
+        digest_constraints,
+        digest_vk.serialized_size(Compress::Yes),
+        digest_verify_time,
+    );
 }
\ No newline at end of file
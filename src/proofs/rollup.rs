@@ -0,0 +1,409 @@
+use std::time::Instant;
+
+use ark_relations::r1cs::{ConstraintSystemRef, ConstraintSynthesizer};
+use ark_bls12_377::{
+    Fr,
+    Bls12_377,
+};
+use ark_std::{
+    rand::{SeedableRng, RngCore},
+    UniformRand,
+};
+use ark_ec::{AffineRepr, CurveGroup};
+use ark_snark::{
+    CircuitSpecificSetupSNARK,
+    SNARK,
+};
+use ark_r1cs_std::{
+    alloc::AllocVar,
+    boolean::Boolean,
+    eq::EqGadget,
+    fields::{fp::FpVar, FieldVar},
+    groups::CurveVar,
+};
+use ark_crypto_primitives::{
+    merkle_tree::{constraints::PathVar, Path},
+    sponge::poseidon::PoseidonConfig,
+};
+use ark_ed_on_bls12_377::{EdwardsAffine, Fr as ScalarF, constraints::EdwardsVar};
+
+use crate::gadgets::{
+    MerkleHash,
+    MerkleParameters,
+    PoseidonMerkle,
+    PoseidonCRHParametersVar,
+    SchnorrSignatureVar,
+    merkle_parameters_var,
+    schnorr_verify_gadget,
+    enforce_range_gadget,
+};
+use crate::utils::schnorr::{schnorr_challenge, schnorr_parameters, schnorr_verify, SchnorrKeyPair, SchnorrSignature};
+use crate::utils::is_satisfied;
+use super::merkle_update::MerkleState;
+
+// Toy rollup: accounts (pk, balance, nonce) are the leaves [pk.x, pk.y, balance, nonce] of a
+// Poseidon Merkle tree. A batch of signed transfers moves the state from root R0 to root R1.
+// Balances and amounts are 64-bit; the signed message is [from, to, amount, nonce].
+
+pub const BALANCE_BITS: usize = 64;
+
+type AccountTree = <PoseidonMerkle as MerkleHash>::P;
+
+#[derive(Clone, Debug)]
+pub struct Account {
+    pub pk: EdwardsAffine,
+    pub balance: u64,
+    pub nonce: u64,
+}
+
+impl Account {
+    // Unused slots: the identity as key. Signatures under it are forgeable, so both the
+    // sequencer and the circuit reject transfers out of empty slots
+    pub fn empty() -> Self {
+        Account { pk: EdwardsAffine::zero(), balance: 0, nonce: 0 }
+    }
+
+    pub fn leaf(&self) -> Vec<Fr> {
+        vec![self.pk.x, self.pk.y, Fr::from(self.balance), Fr::from(self.nonce)]
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Transfer {
+    pub from: usize,
+    pub to: usize,
+    pub amount: u64,
+    pub nonce: u64,
+    pub signature: SchnorrSignature,
+}
+
+impl Transfer {
+    pub fn message(from: usize, to: usize, amount: u64, nonce: u64) -> Vec<Fr> {
+        vec![Fr::from(from as u64), Fr::from(to as u64), Fr::from(amount), Fr::from(nonce)]
+    }
+
+    pub fn sign<R: RngCore>(
+        params: &PoseidonConfig<Fr>,
+        key_pair: &SchnorrKeyPair,
+        from: usize,
+        to: usize,
+        amount: u64,
+        nonce: u64,
+        rng: &mut R,
+    ) -> Self {
+        let signature = key_pair.sign(params, &Transfer::message(from, to, amount, nonce), rng);
+        Transfer { from, to, amount, nonce, signature }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TransferError {
+    UnknownAccount,
+    InvalidSignature,
+    InvalidNonce,
+    InsufficientBalance,
+    BalanceOverflow,
+}
+
+// A transfer with the accounts it reads and their paths, the recipient's taken after the
+// sender's update
+#[derive(Clone)]
+pub struct TransferWitness {
+    pub transfer: Transfer,
+    pub sender: Account,
+    pub sender_path: Path<AccountTree>,
+    pub recipient: Account,
+    pub recipient_path: Path<AccountTree>,
+}
+
+// Native sequencer: holds the accounts and the tree, checks transfers and builds batches
+pub struct Sequencer {
+    pub schnorr: PoseidonConfig<Fr>,
+    pub accounts: Vec<Account>,
+    pub state: MerkleState<PoseidonMerkle>,
+}
+
+impl Sequencer {
+    // `accounts` are padded with empty accounts to 2^depth leaves
+    pub fn new(params: MerkleParameters<AccountTree>, mut accounts: Vec<Account>, depth: usize) -> Self {
+        assert!(accounts.len() <= 1 << depth);
+        accounts.resize(1 << depth, Account::empty());
+        let state = MerkleState::new(params, accounts.iter().map(Account::leaf).collect());
+        Sequencer { schnorr: schnorr_parameters(), accounts, state }
+    }
+
+    pub fn root(&self) -> Fr {
+        self.state.root()
+    }
+
+    // Checks `transfer` against `accounts` and applies it to them
+    fn check(&self, accounts: &mut [Account], transfer: &Transfer) -> Result<(), TransferError> {
+        if transfer.from >= accounts.len() || transfer.to >= accounts.len() {
+            return Err(TransferError::UnknownAccount);
+        }
+        let sender = &accounts[transfer.from];
+        let message = Transfer::message(transfer.from, transfer.to, transfer.amount, transfer.nonce);
+        if sender.pk.is_zero() || !schnorr_verify(&self.schnorr, &sender.pk, &message, &transfer.signature) {
+            return Err(TransferError::InvalidSignature);
+        }
+        if transfer.nonce != sender.nonce {
+            return Err(TransferError::InvalidNonce);
+        }
+        let sender_balance = sender.balance.checked_sub(transfer.amount).ok_or(TransferError::InsufficientBalance)?;
+        accounts[transfer.from].balance = sender_balance;
+        accounts[transfer.from].nonce += 1;
+        let recipient_balance = accounts[transfer.to].balance.checked_add(transfer.amount).ok_or(TransferError::BalanceOverflow)?;
+        accounts[transfer.to].balance = recipient_balance;
+        Ok(())
+    }
+
+    // Applies `transfer` without checks and returns its witness
+    fn apply(&mut self, transfer: &Transfer) -> TransferWitness {
+        let sender = self.accounts[transfer.from].clone();
+        let mut updated_sender = sender.clone();
+        updated_sender.balance = sender.balance.wrapping_sub(transfer.amount);
+        updated_sender.nonce += 1;
+        let sender_path = self.state.apply(&[(transfer.from, updated_sender.leaf())]).remove(0).path;
+        self.accounts[transfer.from] = updated_sender;
+
+        let recipient = self.accounts[transfer.to].clone();
+        let mut updated_recipient = recipient.clone();
+        updated_recipient.balance = recipient.balance.wrapping_add(transfer.amount);
+        let recipient_path = self.state.apply(&[(transfer.to, updated_recipient.leaf())]).remove(0).path;
+        self.accounts[transfer.to] = updated_recipient;
+
+        TransferWitness { transfer: transfer.clone(), sender, sender_path, recipient, recipient_path }
+    }
+
+    // Builds the batch of `transfers`, in order. If one of them is invalid, returns its
+    // position and the reason, and leaves the state unchanged.
+    pub fn build_batch(&mut self, transfers: &[Transfer]) -> Result<RollupCircuit, (usize, TransferError)> {
+        let mut accounts = self.accounts.clone();
+        for (i, transfer) in transfers.iter().enumerate() {
+            self.check(&mut accounts, transfer).map_err(|e| (i, e))?;
+        }
+
+        let old_root = self.root();
+        let transfers = transfers.iter().map(|transfer| self.apply(transfer)).collect();
+        Ok(RollupCircuit {
+            params: self.state.params.clone(),
+            schnorr: self.schnorr.clone(),
+            old_root,
+            new_root: self.root(),
+            transfers,
+        })
+    }
+}
+
+// R0 and R1 are public inputs; the transfers, accounts and paths are witnesses
+#[derive(Clone)]
+pub struct RollupCircuit {
+    pub params: MerkleParameters<AccountTree>,
+    pub schnorr: PoseidonConfig<Fr>,
+    pub old_root: Fr,
+    pub new_root: Fr,
+    pub transfers: Vec<TransferWitness>,
+}
+
+impl RollupCircuit {
+    // Placeholder circuit for setup: a batch of `batch_size` transfers on a fresh tree
+    pub fn blank<R: RngCore>(params: MerkleParameters<AccountTree>, depth: usize, batch_size: usize, rng: &mut R) -> Self {
+        let key_pair = SchnorrKeyPair::generate(rng);
+        let account = Account { pk: key_pair.pk, balance: batch_size as u64, nonce: 0 };
+        let mut sequencer = Sequencer::new(params, vec![account], depth);
+        let transfers: Vec<Transfer> = (0..batch_size as u64)
+            .map(|nonce| Transfer::sign(&sequencer.schnorr, &key_pair, 0, 1, 1, nonce, rng))
+            .collect();
+        sequencer.build_batch(&transfers).unwrap()
+    }
+
+    pub fn public_inputs(&self) -> Vec<Fr> {
+        vec![self.old_root, self.new_root]
+    }
+}
+
+fn account_leaf_var(pk_x: &FpVar<Fr>, pk_y: &FpVar<Fr>, balance: &FpVar<Fr>, nonce: &FpVar<Fr>) -> Vec<FpVar<Fr>> {
+    vec![pk_x.clone(), pk_y.clone(), balance.clone(), nonce.clone()]
+}
+
+impl ConstraintSynthesizer<Fr> for RollupCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> ark_relations::r1cs::Result<()> {
+        let (leaf_params_var, two_to_one_params_var) = merkle_parameters_var::<PoseidonMerkle>(cs.clone(), &self.params)?;
+        let schnorr_params_var = PoseidonCRHParametersVar::new_constant(ark_relations::ns!(cs, "Schnorr Parameters"), &self.schnorr)?;
+        let old_root_var = PoseidonMerkle::root_input(cs.clone(), Some(&self.old_root))?;
+        let new_root_var = PoseidonMerkle::root_input(cs.clone(), Some(&self.new_root))?;
+
+        let mut root_var = old_root_var;
+        for witness in self.transfers.iter() {
+            let transfer = &witness.transfer;
+
+            // Sender: the signature on [from, to, amount, nonce] is checked against the
+            // key in the sender's leaf, and the nonce is the one in the leaf
+            let sender_pk = EdwardsVar::new_witness(ark_relations::ns!(cs, "sender_pk"), || Ok(witness.sender.pk))?;
+            // Empty slots have the identity as key, for which any (s, H([s]G, O, msg)) verifies
+            sender_pk.is_eq(&EdwardsVar::zero())?.enforce_equal(&Boolean::FALSE)?;
+            let sender_balance = FpVar::new_witness(ark_relations::ns!(cs, "sender_balance"), || Ok(Fr::from(witness.sender.balance)))?;
+            let sender_nonce = FpVar::new_witness(ark_relations::ns!(cs, "sender_nonce"), || Ok(Fr::from(witness.sender.nonce)))?;
+            let sender_path = PathVar::<AccountTree, Fr, <PoseidonMerkle as MerkleHash>::PG>::new_witness(
+                ark_relations::ns!(cs, "sender_path"),
+                || Ok(&witness.sender_path),
+            )?;
+            let recipient_path = PathVar::<AccountTree, Fr, <PoseidonMerkle as MerkleHash>::PG>::new_witness(
+                ark_relations::ns!(cs, "recipient_path"),
+                || Ok(&witness.recipient_path),
+            )?;
+            let amount = FpVar::new_witness(ark_relations::ns!(cs, "amount"), || Ok(Fr::from(transfer.amount)))?;
+            enforce_range_gadget(&amount, BALANCE_BITS)?;
+            let signature = SchnorrSignatureVar::new_witness(ark_relations::ns!(cs, "signature"), || Ok(&transfer.signature))?;
+
+            let from = Boolean::le_bits_to_fp_var(&sender_path.get_leaf_position())?;
+            let to = Boolean::le_bits_to_fp_var(&recipient_path.get_leaf_position())?;
+            let message = vec![from, to, amount.clone(), sender_nonce.clone()];
+            schnorr_verify_gadget(&schnorr_params_var, &sender_pk, &message, &signature)?.enforce_equal(&Boolean::TRUE)?;
+
+            // No underflow: the new balance must still fit in 64 bits
+            let sender_new_balance = &sender_balance - &amount;
+            enforce_range_gadget(&sender_new_balance, BALANCE_BITS)?;
+            let sender_new_nonce = &sender_nonce + FpVar::one();
+
+            root_var = sender_path.update_leaf(
+                &leaf_params_var,
+                &two_to_one_params_var,
+                &root_var,
+                &account_leaf_var(&sender_pk.x, &sender_pk.y, &sender_balance, &sender_nonce),
+                &account_leaf_var(&sender_pk.x, &sender_pk.y, &sender_new_balance, &sender_new_nonce),
+            )?;
+
+            // Recipient, in the tree left by the sender's update
+            let recipient_pk_x = FpVar::new_witness(ark_relations::ns!(cs, "recipient_pk_x"), || Ok(witness.recipient.pk.x))?;
+            let recipient_pk_y = FpVar::new_witness(ark_relations::ns!(cs, "recipient_pk_y"), || Ok(witness.recipient.pk.y))?;
+            let recipient_balance = FpVar::new_witness(ark_relations::ns!(cs, "recipient_balance"), || Ok(Fr::from(witness.recipient.balance)))?;
+            let recipient_nonce = FpVar::new_witness(ark_relations::ns!(cs, "recipient_nonce"), || Ok(Fr::from(witness.recipient.nonce)))?;
+
+            // No overflow
+            let recipient_new_balance = &recipient_balance + &amount;
+            enforce_range_gadget(&recipient_new_balance, BALANCE_BITS)?;
+
+            root_var = recipient_path.update_leaf(
+                &leaf_params_var,
+                &two_to_one_params_var,
+                &root_var,
+                &account_leaf_var(&recipient_pk_x, &recipient_pk_y, &recipient_balance, &recipient_nonce),
+                &account_leaf_var(&recipient_pk_x, &recipient_pk_y, &recipient_new_balance, &recipient_nonce),
+            )?;
+        }
+        root_var.enforce_equal(&new_root_var)?;
+
+        Ok(())
+    }
+}
+
+pub fn test_rollup_with_proof() {
+    println!("\n### Running test_rollup_with_proof()...");
+
+    use ark_groth16::Groth16;
+
+    let mut rng = ark_std::rand::rngs::StdRng::seed_from_u64(ark_std::test_rng().next_u64());
+
+    const DEPTH: usize = 8;
+    const BATCH_SIZE: usize = 4;
+
+    let params = PoseidonMerkle::setup(&mut rng);
+    let schnorr = schnorr_parameters();
+    let key_pairs: Vec<SchnorrKeyPair> = (0..4).map(|_| SchnorrKeyPair::generate(&mut rng)).collect();
+    let accounts = key_pairs
+        .iter()
+        .map(|key_pair| Account { pk: key_pair.pk, balance: 100, nonce: 0 })
+        .collect();
+    let mut sequencer = Sequencer::new(params.clone(), accounts, DEPTH);
+
+    // The sequencer rejects invalid transfers and keeps its state
+    let root = sequencer.root();
+    let invalid = [
+        (Transfer::sign(&schnorr, &key_pairs[0], 0, 1, 101, 0, &mut rng), TransferError::InsufficientBalance),
+        (Transfer::sign(&schnorr, &key_pairs[0], 0, 1, 10, 1, &mut rng), TransferError::InvalidNonce),
+        (Transfer::sign(&schnorr, &key_pairs[1], 0, 1, 10, 0, &mut rng), TransferError::InvalidSignature),
+        (Transfer::sign(&schnorr, &key_pairs[0], 0, 1 << DEPTH, 10, 0, &mut rng), TransferError::UnknownAccount),
+    ];
+    for (transfer, error) in invalid {
+        let batch = [Transfer::sign(&schnorr, &key_pairs[2], 2, 3, 1, 0, &mut rng), transfer];
+        assert_eq!(sequencer.build_batch(&batch).err(), Some((1, error)));
+        assert_eq!(sequencer.root(), root);
+    }
+
+    let (pk, vk) = Groth16::<Bls12_377>::setup(RollupCircuit::blank(params.clone(), DEPTH, BATCH_SIZE, &mut rng), &mut rng).unwrap();
+
+    // Two batches; a replay of an included transfer is rejected (stale nonce)
+    let first = [
+        Transfer::sign(&schnorr, &key_pairs[0], 0, 1, 30, 0, &mut rng),
+        Transfer::sign(&schnorr, &key_pairs[1], 1, 2, 130, 0, &mut rng),
+        Transfer::sign(&schnorr, &key_pairs[0], 0, 9, 70, 1, &mut rng),
+        Transfer::sign(&schnorr, &key_pairs[3], 3, 3, 5, 0, &mut rng),
+    ];
+    let second = [
+        Transfer::sign(&schnorr, &key_pairs[2], 2, 0, 230, 0, &mut rng),
+        Transfer::sign(&schnorr, &key_pairs[0], 0, 1, 230, 2, &mut rng),
+        Transfer::sign(&schnorr, &key_pairs[1], 1, 3, 230, 1, &mut rng),
+        first[3].clone(),
+    ];
+    let circuit = sequencer.build_batch(&first).unwrap();
+    assert_eq!(sequencer.build_batch(&second).err(), Some((3, TransferError::InvalidNonce)));
+
+    let (_, constraints) = is_satisfied(circuit.clone()).unwrap();
+    let start = Instant::now();
+    let proof = Groth16::<Bls12_377>::prove(&pk, circuit.clone(), &mut rng).unwrap();
+    println!("depth {}, {} transfers: {} constraints, prove {} ms", DEPTH, BATCH_SIZE, constraints, start.elapsed().as_millis());
+    assert!(Groth16::<Bls12_377>::verify(&vk, circuit.public_inputs().as_slice(), &proof).unwrap());
+
+    let mut second = second.to_vec();
+    second[3] = Transfer::sign(&schnorr, &key_pairs[3], 3, 0, 5, 1, &mut rng);
+    let next = sequencer.build_batch(&second).unwrap();
+    let proof = Groth16::<Bls12_377>::prove(&pk, next.clone(), &mut rng).unwrap();
+    assert!(Groth16::<Bls12_377>::verify(&vk, next.public_inputs().as_slice(), &proof).unwrap());
+    assert!(!Groth16::<Bls12_377>::verify(&vk, circuit.public_inputs().as_slice(), &proof).unwrap());
+    assert_eq!(sequencer.accounts[0].balance, 5);
+    assert_eq!(sequencer.accounts[0].nonce, 3);
+    assert_eq!(sequencer.accounts[3].balance, 325);
+    assert_eq!(sequencer.accounts[9].balance, 70);
+
+    // Transfers the sequencer would reject do not satisfy the circuit either
+    let mut full_recipient = sequencer.accounts.clone();
+    full_recipient[2].balance = u64::MAX - 5;
+    // With the identity as key, R = [s]G: anyone can pick s and compute e = H(R, O, msg)
+    let s = ScalarF::rand(&mut rng);
+    let r = (EdwardsAffine::generator() * s).into_affine();
+    let message = Transfer::message(9, 0, 70, 0);
+    let empty_slot_signature = SchnorrSignature { s, e: schnorr_challenge(&schnorr, &r, &EdwardsAffine::zero(), &message) };
+    assert!(schnorr_verify(&schnorr, &EdwardsAffine::zero(), &message, &empty_slot_signature));
+    let forged_batches = [
+        // overdraft (the native balance wraps around)
+        (sequencer.accounts.clone(), Transfer::sign(&schnorr, &key_pairs[1], 1, 2, 1000, 2, &mut rng), TransferError::InsufficientBalance),
+        // signed by someone else
+        (sequencer.accounts.clone(), Transfer::sign(&schnorr, &key_pairs[2], 1, 2, 10, 2, &mut rng), TransferError::InvalidSignature),
+        // stale nonce
+        (sequencer.accounts.clone(), Transfer::sign(&schnorr, &key_pairs[3], 3, 2, 10, 1, &mut rng), TransferError::InvalidNonce),
+        // recipient balance overflow
+        (full_recipient, Transfer::sign(&schnorr, &key_pairs[3], 3, 2, 10, 2, &mut rng), TransferError::BalanceOverflow),
+        // spend from an empty slot that received funds (account 9)
+        (
+            sequencer.accounts.clone(),
+            Transfer { from: 9, to: 0, amount: 70, nonce: 0, signature: empty_slot_signature },
+            TransferError::InvalidSignature,
+        ),
+    ];
+    for (accounts, transfer, error) in forged_batches {
+        let mut forger = Sequencer::new(params.clone(), accounts, DEPTH);
+        assert_eq!(forger.build_batch(std::slice::from_ref(&transfer)).err(), Some((0, error)));
+
+        let old_root = forger.root();
+        let witness = forger.apply(&transfer);
+        let forged = RollupCircuit {
+            params: params.clone(),
+            schnorr: schnorr.clone(),
+            old_root,
+            new_root: forger.root(),
+            transfers: vec![witness],
+        };
+        assert!(!is_satisfied(forged).unwrap().0);
+    }
+}
@@ -0,0 +1,134 @@
+use ark_relations::r1cs::{ConstraintSystemRef, ConstraintSynthesizer};
+use ark_bls12_377::{
+    Fr,
+    Bls12_377,
+};
+use ark_std::{
+    rand::{SeedableRng, RngCore},
+    UniformRand,
+};
+use ark_snark::{
+    CircuitSpecificSetupSNARK,
+    SNARK,
+};
+use ark_r1cs_std::{
+    alloc::AllocVar,
+    boolean::Boolean,
+    eq::EqGadget,
+    uint8::UInt8,
+};
+use ark_crypto_primitives::signature::{constraints::SigVerifyGadget, SignatureScheme};
+use ark_ed_on_bls12_377::{EdwardsAffine, Fr as ScalarF, constraints::EdwardsVar};
+use ark_ff::ToConstraintField;
+
+use crate::gadgets::{Blake2sSchnorr, Blake2sSchnorrParametersVar, Blake2sSchnorrSignatureVar, Blake2sSchnorrVerifyGadget};
+use crate::utils::is_satisfied;
+
+type SchnorrParameters = <Blake2sSchnorr as SignatureScheme>::Parameters;
+type SchnorrSignature = <Blake2sSchnorr as SignatureScheme>::Signature;
+
+// Knowledge of a Schnorr signature on a message under a public key.
+// The key (x, y) and the message (packed as UInt8::new_input_vec) are public inputs;
+// the signature is the witness.
+#[derive(Clone)]
+pub struct SchnorrSignatureCircuit {
+    pub params: SchnorrParameters,
+    pub pk: EdwardsAffine,
+    pub message: Vec<u8>,
+    pub signature: SchnorrSignature,
+}
+
+impl SchnorrSignatureCircuit {
+    // Placeholder circuit for setup.
+    // NOTE: The message length is fixed, it is part of the hashed data
+    pub fn blank(params: SchnorrParameters, message_len: usize) -> Self {
+        SchnorrSignatureCircuit {
+            params,
+            pk: EdwardsAffine::default(),
+            message: vec![0; message_len],
+            signature: SchnorrSignature {
+                prover_response: ScalarF::from(0u64),
+                verifier_challenge: ScalarF::from(0u64),
+            },
+        }
+    }
+
+    pub fn public_inputs(&self) -> Vec<Fr> {
+        let mut inputs = vec![self.pk.x, self.pk.y];
+        let message: Vec<Fr> = self.message.to_field_elements().unwrap();
+        inputs.extend(message);
+        inputs
+    }
+}
+
+impl ConstraintSynthesizer<Fr> for SchnorrSignatureCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> ark_relations::r1cs::Result<()> {
+        let params_var = Blake2sSchnorrParametersVar::new_constant(ark_relations::ns!(cs, "parameters"), &self.params)?;
+        let pk_var = EdwardsVar::new_input(ark_relations::ns!(cs, "pk"), || Ok(self.pk))?;
+        let message_var = UInt8::new_input_vec(ark_relations::ns!(cs, "message"), &self.message)?;
+        let signature_var = Blake2sSchnorrSignatureVar::new_witness(ark_relations::ns!(cs, "signature"), || Ok(&self.signature))?;
+
+        Blake2sSchnorrVerifyGadget::verify(&params_var, &pk_var, &message_var, &signature_var)?
+            .enforce_equal(&Boolean::TRUE)?;
+
+        Ok(())
+    }
+}
+
+pub fn test_schnorr_signature_with_proof() {
+    println!("\n### Running test_schnorr_signature_with_proof()...");
+
+    use ark_groth16::Groth16;
+
+    let mut rng = ark_std::rand::rngs::StdRng::seed_from_u64(ark_std::test_rng().next_u64());
+
+    const MESSAGE_LEN: usize = 32;
+
+    // The parameters (generator and salt) are circuit constants
+    let params = Blake2sSchnorr::setup(&mut rng).unwrap();
+    let (pk, vk) = Groth16::<Bls12_377>::setup(SchnorrSignatureCircuit::blank(params.clone(), MESSAGE_LEN), &mut rng).unwrap();
+
+    let (public_key, secret_key) = Blake2sSchnorr::keygen(&params, &mut rng).unwrap();
+    let (other_public_key, other_secret_key) = Blake2sSchnorr::keygen(&params, &mut rng).unwrap();
+    let message: Vec<u8> = (0..MESSAGE_LEN).map(|_| u8::rand(&mut rng)).collect();
+    let signature = Blake2sSchnorr::sign(&params, &secret_key, &message, &mut rng).unwrap();
+
+    let circuit = SchnorrSignatureCircuit { params: params.clone(), pk: public_key, message: message.clone(), signature: signature.clone() };
+    let proof = Groth16::<Bls12_377>::prove(&pk, circuit.clone(), &mut rng).unwrap();
+    assert!(Groth16::<Bls12_377>::verify(&vk, circuit.public_inputs().as_slice(), &proof).unwrap());
+
+    // The proof is tied to the key and the message
+    let mut other_message = message.clone();
+    other_message[MESSAGE_LEN - 1] ^= 0x80;
+    let other_key = SchnorrSignatureCircuit { pk: other_public_key, ..circuit.clone() };
+    let other = SchnorrSignatureCircuit { message: other_message.clone(), ..circuit.clone() };
+    assert!(!Groth16::<Bls12_377>::verify(&vk, other_key.public_inputs().as_slice(), &proof).unwrap());
+    assert!(!Groth16::<Bls12_377>::verify(&vk, other.public_inputs().as_slice(), &proof).unwrap());
+
+    // Forged signatures do not satisfy the circuit
+    let mut forged_response = signature.clone();
+    forged_response.prover_response += ScalarF::from(1u64);
+    let mut forged_challenge = signature.clone();
+    forged_challenge.verifier_challenge -= ScalarF::from(1u64);
+    let mut other_salt = params.clone();
+    other_salt.salt = [0; 32];
+    let forgeries = [
+        other_key,
+        other,
+        SchnorrSignatureCircuit { signature: forged_response, ..circuit.clone() },
+        SchnorrSignatureCircuit { signature: forged_challenge, ..circuit.clone() },
+        SchnorrSignatureCircuit {
+            signature: Blake2sSchnorr::sign(&params, &other_secret_key, &message, &mut rng).unwrap(),
+            ..circuit.clone()
+        },
+        // A valid signature under other parameters (another salt)
+        SchnorrSignatureCircuit {
+            signature: Blake2sSchnorr::sign(&other_salt, &secret_key, &message, &mut rng).unwrap(),
+            ..circuit.clone()
+        },
+    ];
+    for forged in forgeries {
+        assert!(!Blake2sSchnorr::verify(&params, &forged.pk, &forged.message, &forged.signature).unwrap());
+        assert!(!is_satisfied(forged).unwrap().0);
+    }
+}
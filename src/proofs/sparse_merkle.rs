@@ -0,0 +1,162 @@
+use std::time::Instant;
+
+use ark_relations::r1cs::{ConstraintSystemRef, ConstraintSynthesizer};
+use ark_bls12_377::{
+    Fr,
+    Bls12_377,
+};
+use ark_std::rand::{SeedableRng, RngCore};
+use ark_snark::{
+    CircuitSpecificSetupSNARK,
+    SNARK,
+};
+use ark_r1cs_std::{
+    alloc::AllocVar,
+    fields::fp::FpVar,
+    uint8::UInt8,
+};
+use ark_crypto_primitives::sponge::poseidon::PoseidonConfig;
+use ark_ff::{ToConstraintField, Zero};
+
+use crate::gadgets::{PoseidonCRHParametersVar, smt_membership_gadget, smt_non_membership_gadget};
+use crate::utils::sparse_merkle::{smt_parameters, random_key, SparseMerkleKey, SparseMerklePath, SparseMerkleTree, SMT_DEPTH};
+use crate::utils::is_satisfied;
+
+// Membership (value = Some) or non-membership (value = None) of a key in a sparse Merkle tree.
+// The root and the key are public inputs; the value and the siblings are witnesses.
+// Which statement is proven is fixed at setup.
+#[derive(Clone)]
+pub struct SparseMerkleCircuit {
+    pub params: PoseidonConfig<Fr>,
+    pub root: Fr,
+    pub key: SparseMerkleKey,
+    pub value: Option<Fr>,
+    pub path: SparseMerklePath,
+}
+
+impl SparseMerkleCircuit {
+    pub fn membership(tree: &SparseMerkleTree, key: SparseMerkleKey) -> Self {
+        SparseMerkleCircuit {
+            params: tree.params.clone(),
+            root: tree.root(),
+            key,
+            value: Some(tree.get(&key).unwrap()),
+            path: tree.prove(&key),
+        }
+    }
+
+    pub fn non_membership(tree: &SparseMerkleTree, key: SparseMerkleKey) -> Self {
+        assert!(tree.get(&key).is_none());
+        SparseMerkleCircuit {
+            params: tree.params.clone(),
+            root: tree.root(),
+            key,
+            value: None,
+            path: tree.prove(&key),
+        }
+    }
+
+    // Placeholder circuit for setup
+    pub fn blank(params: PoseidonConfig<Fr>, membership: bool) -> Self {
+        SparseMerkleCircuit {
+            params,
+            root: Fr::zero(),
+            key: [0u8; 32],
+            value: membership.then(Fr::zero),
+            path: SparseMerklePath { siblings: vec![Fr::zero(); SMT_DEPTH] },
+        }
+    }
+
+    // Root, then the key packed as UInt8::new_input_vec
+    pub fn public_inputs(&self) -> Vec<Fr> {
+        let mut inputs = vec![self.root];
+        let key: Vec<Fr> = self.key.to_field_elements().unwrap();
+        inputs.extend(key);
+        inputs
+    }
+}
+
+impl ConstraintSynthesizer<Fr> for SparseMerkleCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> ark_relations::r1cs::Result<()> {
+        let params_var = PoseidonCRHParametersVar::new_constant(ark_relations::ns!(cs, "CRH Parameters"), &self.params)?;
+        let root_var = FpVar::new_input(ark_relations::ns!(cs, "root"), || Ok(self.root))?;
+        let key_var = UInt8::new_input_vec(ark_relations::ns!(cs, "key"), &self.key)?;
+        let siblings_var = Vec::<FpVar<Fr>>::new_witness(
+            ark_relations::ns!(cs, "siblings"),
+            || Ok(self.path.siblings.as_slice()),
+        )?;
+
+        match self.value {
+            Some(value) => {
+                let value_var = FpVar::new_witness(ark_relations::ns!(cs, "value"), || Ok(value))?;
+                smt_membership_gadget(&params_var, &root_var, &key_var, &value_var, &siblings_var)?;
+            }
+            None => smt_non_membership_gadget(&params_var, &root_var, &key_var, &siblings_var)?,
+        }
+
+        Ok(())
+    }
+}
+
+pub fn test_sparse_merkle_with_proof() {
+    println!("\n### Running test_sparse_merkle_with_proof()...");
+
+    use ark_groth16::Groth16;
+
+    let mut rng = ark_std::rand::rngs::StdRng::seed_from_u64(ark_std::test_rng().next_u64());
+
+    let mut tree = SparseMerkleTree::new(smt_parameters());
+    let keys: Vec<SparseMerkleKey> = (0..64).map(|_| random_key(&mut rng)).collect();
+    for (i, key) in keys.iter().enumerate() {
+        tree.insert(*key, Fr::from(i as u64 + 1));
+    }
+    let absent = random_key(&mut rng);
+
+    // Non-membership
+    let (pk, vk) = Groth16::<Bls12_377>::setup(SparseMerkleCircuit::blank(smt_parameters(), false), &mut rng).unwrap();
+
+    let circuit = SparseMerkleCircuit::non_membership(&tree, absent);
+    let start = Instant::now();
+    let proof = Groth16::<Bls12_377>::prove(&pk, circuit.clone(), &mut rng).unwrap();
+    println!("non-membership: prove {} ms", start.elapsed().as_millis());
+    assert!(Groth16::<Bls12_377>::verify(&vk, circuit.public_inputs().as_slice(), &proof).unwrap());
+
+    // The proof is tied to the key and the root
+    let other_key = SparseMerkleCircuit::non_membership(&tree, random_key(&mut rng));
+    assert!(!Groth16::<Bls12_377>::verify(&vk, other_key.public_inputs().as_slice(), &proof).unwrap());
+
+    // Once the key is inserted, its absence can no longer be shown against the new root
+    let mut updated = tree.clone();
+    updated.insert(absent, Fr::from(1000u64));
+    let present = SparseMerkleCircuit {
+        root: updated.root(),
+        path: updated.prove(&absent),
+        ..circuit.clone()
+    };
+    assert!(!Groth16::<Bls12_377>::verify(&vk, present.public_inputs().as_slice(), &proof).unwrap());
+    assert!(!is_satisfied(present).unwrap().0);
+
+    // Present keys have no non-membership witness
+    let forged = SparseMerkleCircuit {
+        key: keys[0],
+        path: tree.prove(&keys[0]),
+        ..circuit.clone()
+    };
+    assert!(!is_satisfied(forged).unwrap().0);
+
+    // Membership
+    let (pk, vk) = Groth16::<Bls12_377>::setup(SparseMerkleCircuit::blank(smt_parameters(), true), &mut rng).unwrap();
+
+    let circuit = SparseMerkleCircuit::membership(&tree, keys[7]);
+    let start = Instant::now();
+    let proof = Groth16::<Bls12_377>::prove(&pk, circuit.clone(), &mut rng).unwrap();
+    println!("membership: prove {} ms", start.elapsed().as_millis());
+    assert!(Groth16::<Bls12_377>::verify(&vk, circuit.public_inputs().as_slice(), &proof).unwrap());
+
+    let wrong_value = SparseMerkleCircuit {
+        value: Some(Fr::from(9u64)),
+        ..circuit.clone()
+    };
+    assert!(!is_satisfied(wrong_value).unwrap().0);
+    assert!(!Groth16::<Bls12_377>::verify(&vk, SparseMerkleCircuit::membership(&tree, keys[8]).public_inputs().as_slice(), &proof).unwrap());
+}
@@ -0,0 +1,124 @@
+use ark_bls12_377::{
+    g1::Config as G1Config,
+    Bls12_377,
+    Fq,
+    Fr,
+    G1Affine,
+    G1Projective,
+    G2Projective,
+};
+use ark_crypto_primitives::crh::{sha256::Sha256, CRHScheme};
+use ark_ec::{
+    pairing::Pairing,
+    short_weierstrass::SWCurveConfig,
+    AffineRepr,
+    CurveGroup,
+    Group,
+};
+use ark_ff::{BigInteger, Field, PrimeField};
+use ark_std::{rand::Rng, UniformRand};
+
+// Native BLS signatures over BLS12-377 (signatures in G1, public keys in G2).
+// Used to produce test vectors for the circuits in proofs::bls.
+
+// Number of x candidates tried by hash_to_g1 before giving up.
+// The in-circuit version unrolls exactly this many attempts.
+pub const HASH_TO_G1_MAX_TRIES: u64 = 32;
+
+#[derive(Clone, Debug)]
+pub struct BlsKeyPair {
+    pub sk: Fr,
+    pub pk: G2Projective,
+}
+
+impl BlsKeyPair {
+    pub fn generate<R: Rng>(rng: &mut R) -> Self {
+        let sk = Fr::rand(rng);
+        let pk = G2Projective::generator() * sk;
+        BlsKeyPair { sk, pk }
+    }
+
+    pub fn sign(&self, msg: &[u8]) -> G1Projective {
+        sign(&self.sk, msg)
+    }
+}
+
+// Try-and-increment hash to G1:
+//  x_0 = SHA256(msg) as an Fq element (little endian), x_i = x_0 + i
+//  take the first x_i such that x_i^3 + b is a square, pick the even root y
+//  and clear the cofactor of (x_i, y)
+pub fn hash_to_g1(msg: &[u8]) -> Option<G1Projective> {
+    let (x, y) = try_and_increment(hash_to_field(msg))?;
+    let point = G1Affine::new_unchecked(x, y);
+    Some(point.clear_cofactor().into_group())
+}
+
+pub fn hash_to_field(msg: &[u8]) -> Fq {
+    let digest = Sha256::evaluate(&(), msg).unwrap();
+    Fq::from_le_bytes_mod_order(&digest)
+}
+
+// Returns the (x, y) point found from x_0, before cofactor clearing
+pub fn try_and_increment(x_0: Fq) -> Option<(Fq, Fq)> {
+    for i in 0..HASH_TO_G1_MAX_TRIES {
+        let x = x_0 + Fq::from(i);
+        let rhs = x.square() * x + G1Config::COEFF_B;
+        if let Some(y) = rhs.sqrt() {
+            let y = if y.into_bigint().is_odd() { -y } else { y };
+            return Some((x, y));
+        }
+    }
+    None
+}
+
+pub fn sign(sk: &Fr, msg: &[u8]) -> G1Projective {
+    hash_to_g1(msg).unwrap() * sk
+}
+
+// Checks e(sig, g2) == e(H(msg), pk)
+pub fn verify(pk: &G2Projective, msg: &[u8], sig: &G1Projective) -> bool {
+    match hash_to_g1(msg) {
+        Some(h) => verify_prehashed(pk, &h, sig),
+        None => false,
+    }
+}
+
+pub fn verify_prehashed(pk: &G2Projective, msg_hash: &G1Projective, sig: &G1Projective) -> bool {
+    let lhs = Bls12_377::pairing(sig, G2Projective::generator());
+    let rhs = Bls12_377::pairing(msg_hash, pk);
+    lhs == rhs
+}
+
+// Sum of the public keys selected by `bitmap`
+pub fn aggregate_public_keys(committee: &[G2Projective], bitmap: &[bool]) -> G2Projective {
+    committee
+        .iter()
+        .zip(bitmap)
+        .filter(|(_, bit)| **bit)
+        .map(|(pk, _)| *pk)
+        .sum()
+}
+
+pub fn aggregate_signatures(signatures: &[G1Projective]) -> G1Projective {
+    signatures.iter().sum()
+}
+
+// Field elements produced by G1Var::new_input: (x, y, z) with z = 1, or (0, 1, 0) for zero
+pub fn g1_to_field_elements(p: &G1Projective) -> Vec<Fq> {
+    let p = p.into_affine();
+    if p.is_zero() {
+        vec![Fq::ZERO, Fq::ONE, Fq::ZERO]
+    } else {
+        vec![p.x, p.y, Fq::ONE]
+    }
+}
+
+// Field elements produced by G2Var::new_input: (x.c0, x.c1, y.c0, y.c1, z.c0, z.c1)
+pub fn g2_to_field_elements(p: &G2Projective) -> Vec<Fq> {
+    let p = p.into_affine();
+    if p.is_zero() {
+        vec![Fq::ZERO, Fq::ZERO, Fq::ONE, Fq::ZERO, Fq::ZERO, Fq::ZERO]
+    } else {
+        vec![p.x.c0, p.x.c1, p.y.c0, p.y.c1, Fq::ONE, Fq::ZERO]
+    }
+}
@@ -0,0 +1,115 @@
+use ark_bls12_377::Fr;
+use ark_crypto_primitives::{
+    crh::{sha256::Sha256, CRHScheme},
+    sponge::poseidon::PoseidonConfig,
+};
+use ark_ec::{AffineRepr, CurveGroup, Group};
+use ark_ed_on_bls12_377::{EdwardsAffine, EdwardsProjective, Fr as ScalarF};
+use ark_ff::{BigInteger, PrimeField, ToConstraintField, Zero};
+use ark_serialize::CanonicalSerialize;
+use ark_std::rand::Rng;
+
+use crate::gadgets::PoseidonCRH;
+
+// Native EdDSA on ed_on_bls12_377, after RFC 8032 (Ed25519) with SHA-256 instead of SHA-512:
+//  keys:   (a, prefix) = (H(seed || 0) || H(seed || 1)) mod l and H(seed || 2), A = a B
+//  sign:   r = (H(prefix || M || 0) || H(prefix || M || 1)) mod l, R = r B,
+//          k = H'(R, A, M), S = r + k a mod l
+//  verify: S < l and [4][S]B == [4](R + [k]A)  (cofactored, as the circuit checks it)
+// where H' is the challenge hash, either SHA-256(compress(R) || compress(A) || M) or
+// Poseidon(R.x, R.y, A.x, A.y, M packed 31 bytes per element). The challenge is used as a
+// 256-bit integer in-circuit and reduced modulo l natively, which agree after the cofactor.
+// The generator B is the ed_on_bls12_377 generator. No clamping: the scalar is reduced instead.
+
+pub const COFACTOR_BITS: usize = 2;
+
+#[derive(Clone)]
+pub enum EddsaHash {
+    Sha256,
+    Poseidon(PoseidonConfig<Fr>),
+}
+
+impl EddsaHash {
+    pub fn name(&self) -> &'static str {
+        match self {
+            EddsaHash::Sha256 => "sha256",
+            EddsaHash::Poseidon(_) => "poseidon",
+        }
+    }
+
+    // The challenge as 32 little-endian bytes
+    pub fn challenge(&self, r: &EdwardsAffine, pk: &EdwardsAffine, msg: &[u8]) -> Vec<u8> {
+        match self {
+            EddsaHash::Sha256 => {
+                let mut input = Vec::new();
+                r.serialize_compressed(&mut input).unwrap();
+                pk.serialize_compressed(&mut input).unwrap();
+                input.extend_from_slice(msg);
+                Sha256::evaluate(&(), input).unwrap()
+            }
+            EddsaHash::Poseidon(params) => {
+                let mut input = vec![r.x, r.y, pk.x, pk.y];
+                let msg: Vec<Fr> = msg.to_field_elements().unwrap();
+                input.extend(msg);
+                PoseidonCRH::evaluate(params, input).unwrap().into_bigint().to_bytes_le()
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EddsaSignature {
+    pub r: EdwardsAffine,
+    pub s: ScalarF,
+}
+
+#[derive(Clone, Debug)]
+pub struct EddsaKeyPair {
+    pub seed: [u8; 32],
+    pub a: ScalarF,
+    pub prefix: Vec<u8>,
+    pub pk: EdwardsAffine,
+}
+
+fn sha256(parts: &[&[u8]]) -> Vec<u8> {
+    Sha256::evaluate(&(), parts.concat()).unwrap()
+}
+
+// 512 bits of SHA-256 output reduced modulo l, so that the bias is negligible
+fn wide_hash_to_scalar(parts: &[&[u8]]) -> ScalarF {
+    let mut bytes = sha256(&[parts, &[&[0u8][..]]].concat());
+    bytes.extend(sha256(&[parts, &[&[1u8][..]]].concat()));
+    ScalarF::from_le_bytes_mod_order(&bytes)
+}
+
+impl EddsaKeyPair {
+    pub fn from_seed(seed: [u8; 32]) -> Self {
+        let a = wide_hash_to_scalar(&[&seed]);
+        let prefix = sha256(&[&seed, &[2u8]]);
+        let pk = (EdwardsAffine::generator() * a).into_affine();
+        EddsaKeyPair { seed, a, prefix, pk }
+    }
+
+    pub fn generate<R: Rng>(rng: &mut R) -> Self {
+        let mut seed = [0u8; 32];
+        rng.fill_bytes(&mut seed);
+        EddsaKeyPair::from_seed(seed)
+    }
+
+    // Deterministic: the nonce is derived from the key and the message
+    pub fn sign(&self, hash: &EddsaHash, msg: &[u8]) -> EddsaSignature {
+        let r = wide_hash_to_scalar(&[&self.prefix, msg]);
+        let big_r = (EdwardsAffine::generator() * r).into_affine();
+        let k = ScalarF::from_le_bytes_mod_order(&hash.challenge(&big_r, &self.pk, msg));
+        EddsaSignature { r: big_r, s: r + k * self.a }
+    }
+}
+
+pub fn eddsa_verify(hash: &EddsaHash, pk: &EdwardsAffine, msg: &[u8], signature: &EddsaSignature) -> bool {
+    let k = ScalarF::from_le_bytes_mod_order(&hash.challenge(&signature.r, pk, msg));
+    let mut difference: EdwardsProjective = EdwardsAffine::generator() * signature.s - signature.r - *pk * k;
+    for _ in 0..COFACTOR_BITS {
+        difference.double_in_place();
+    }
+    difference.is_zero()
+}
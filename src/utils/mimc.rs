@@ -0,0 +1,108 @@
+use ark_ff::{BigInteger, PrimeField};
+
+use crate::utils::keccak::keccak256;
+
+// Native MiMC over a prime field: the x^e block cipher in Miyaguchi-Preneel mode (MiMC-7 for e = 7)
+// and the MiMC-Feistel sponge. Reference for the gadgets in gadgets::crh.
+//
+// NOTE: 7 divides r - 1 for the BLS12-377 scalar field, so x^7 is not a permutation there and
+// MiMC-7 is not a block cipher over it. The Feistel rounds are invertible for any exponent;
+// the plain cipher requires an exponent coprime to r - 1 (11 is the smallest one).
+
+pub const MIMC_SEED: &[u8] = b"mimc";
+
+#[derive(Clone, Debug)]
+pub struct MimcParameters<F: PrimeField> {
+    pub exponent: u64,
+    pub round_constants: Vec<F>,
+}
+
+impl<F: PrimeField> MimcParameters<F> {
+    // Round constants from a Keccak-256 chain: c_0 = 0, c_i = keccak256^i(seed)
+    pub fn new(seed: &[u8], exponent: u64, rounds: usize) -> Self {
+        let mut round_constants = vec![F::zero()];
+        let mut digest = keccak256(seed);
+        for _ in 1..rounds {
+            round_constants.push(F::from_be_bytes_mod_order(&digest));
+            digest = keccak256(&digest);
+        }
+        MimcParameters { exponent, round_constants }
+    }
+
+    // ceil(log_e(r)) rounds, so that the degree of the cipher reaches the field size
+    pub fn cipher(seed: &[u8], exponent: u64) -> Self {
+        let params = Self::new(seed, exponent, mimc_rounds::<F>(exponent));
+        assert!(params.is_permutation(), "x^{} is not a permutation of the field", exponent);
+        params
+    }
+
+    // Twice as many rounds, since each Feistel round only updates half of the state
+    pub fn feistel(seed: &[u8], exponent: u64) -> Self {
+        Self::new(seed, exponent, 2 * mimc_rounds::<F>(exponent))
+    }
+
+    pub fn rounds(&self) -> usize {
+        self.round_constants.len()
+    }
+
+    // x^e is a permutation of F iff gcd(e, r - 1) = 1
+    pub fn is_permutation(&self) -> bool {
+        let mut modulus_minus_one = F::MODULUS;
+        modulus_minus_one.sub_with_borrow(&F::BigInt::from(1u64));
+        let remainder = modulus_minus_one
+            .to_bytes_be()
+            .iter()
+            .fold(0u64, |acc, byte| (acc * 256 + *byte as u64) % self.exponent);
+        let (mut a, mut b) = (self.exponent, remainder);
+        while b != 0 {
+            (a, b) = (b, a % b);
+        }
+        a == 1
+    }
+}
+
+pub fn mimc_rounds<F: PrimeField>(exponent: u64) -> usize {
+    (F::MODULUS_BIT_SIZE as f64 / (exponent as f64).log2()).ceil() as usize
+}
+
+// E_k(x): x <- (x + k + c_i)^e for every round, then + k
+pub fn mimc_cipher<F: PrimeField>(params: &MimcParameters<F>, x: F, k: F) -> F {
+    let mut x = x;
+    for c in params.round_constants.iter() {
+        x = (x + k + c).pow([params.exponent]);
+    }
+    x + k
+}
+
+// Miyaguchi-Preneel: h <- E_h(m) + h + m for every input element, starting from h = 0
+pub fn mimc_hash<F: PrimeField>(params: &MimcParameters<F>, input: &[F]) -> F {
+    let mut h = F::zero();
+    for m in input {
+        h = mimc_cipher(params, *m, h) + h + m;
+    }
+    h
+}
+
+// (x_L, x_R) <- (x_R + (x_L + k + c_i)^e, x_L), without the swap in the last round
+pub fn mimc_feistel<F: PrimeField>(params: &MimcParameters<F>, x_l: F, x_r: F, k: F) -> (F, F) {
+    let (mut x_l, mut x_r) = (x_l, x_r);
+    let rounds = params.rounds();
+    for (i, c) in params.round_constants.iter().enumerate() {
+        let t = x_r + (x_l + k + c).pow([params.exponent]);
+        if i < rounds - 1 {
+            (x_l, x_r) = (t, x_l);
+        } else {
+            x_r = t;
+        }
+    }
+    (x_l, x_r)
+}
+
+// Sponge over the Feistel permutation with rate 1 and capacity 1 (as MiMCSponge, with k = 0)
+pub fn mimc_feistel_hash<F: PrimeField>(params: &MimcParameters<F>, input: &[F]) -> F {
+    let (mut x_l, mut x_r) = (F::zero(), F::zero());
+    for m in input {
+        (x_l, x_r) = mimc_feistel(params, x_l + m, x_r, F::zero());
+    }
+    x_l
+}
@@ -1,6 +1,14 @@
-use ark_relations::r1cs::{ConstraintSystemRef};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystem, ConstraintSystemRef, SynthesisError};
 use ark_ff::Field;
 
+pub mod bls;
+pub mod eddsa;
+pub mod keccak;
+pub mod mimc;
+pub mod poseidon;
+pub mod schnorr;
+pub mod sparse_merkle;
+
 type CS<F> = ConstraintSystemRef<F>;
 
 pub fn print_cs_details<F: Field>(cs: CS<F>) {
@@ -9,4 +17,20 @@ pub fn print_cs_details<F: Field>(cs: CS<F>) {
     println!("Num witness variables: {:#?}", cs.num_witness_variables());
     println!("Optimization goal: {:#?}", cs.optimization_goal());
     println!("Constraint names: {:#?}", cs.constraint_names());
-}
\ No newline at end of file
+}
+
+// Synthesizes `circuit` in a fresh constraint system.
+// Returns whether it is satisfied and its number of constraints.
+pub fn is_satisfied<F: Field, C: ConstraintSynthesizer<F>>(circuit: C) -> Result<(bool, usize), SynthesisError> {
+    let cs = ConstraintSystem::<F>::new_ref();
+    circuit.generate_constraints(cs.clone())?;
+    Ok((cs.is_satisfied()?, cs.num_constraints()))
+}
+
+// Decodes test vectors given as hex strings
+pub fn hex_to_bytes(hex: &str) -> Vec<u8> {
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+        .collect()
+}
@@ -0,0 +1,21 @@
+use ark_crypto_primitives::sponge::poseidon::{find_poseidon_ark_and_mds, PoseidonConfig};
+use ark_ff::PrimeField;
+
+// Round numbers and S-box of the arkworks Poseidon tests (x^17 is a permutation of both
+// BLS12-377 Fr and BW6-761 Fr). Good enough for cost experiments, not audited parameters.
+const FULL_ROUNDS: usize = 8;
+const PARTIAL_ROUNDS: usize = 31;
+const ALPHA: u64 = 17;
+
+// Poseidon parameters with the given rate and capacity 1 (state width = rate + 1).
+// Round constants and MDS matrix come from the Grain LFSR, so they are deterministic.
+pub fn poseidon_parameters<F: PrimeField>(rate: usize) -> PoseidonConfig<F> {
+    let (ark, mds) = find_poseidon_ark_and_mds::<F>(
+        F::MODULUS_BIT_SIZE as u64,
+        rate,
+        FULL_ROUNDS as u64,
+        PARTIAL_ROUNDS as u64,
+        0,
+    );
+    PoseidonConfig::new(FULL_ROUNDS, PARTIAL_ROUNDS, ALPHA, mds, ark, rate, 1)
+}
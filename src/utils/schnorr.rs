@@ -0,0 +1,70 @@
+use ark_bls12_377::Fr;
+use ark_crypto_primitives::{
+    crh::{poseidon, CRHScheme},
+    sponge::poseidon::PoseidonConfig,
+};
+use ark_ec::{AffineRepr, CurveGroup};
+use ark_ed_on_bls12_377::{
+    EdwardsAffine,
+    EdwardsProjective,
+    Fr as ScalarF,
+};
+use ark_ff::{BigInteger, PrimeField};
+use ark_std::{rand::Rng, UniformRand};
+
+use crate::utils::poseidon::poseidon_parameters;
+
+// Native Schnorr signatures on ed_on_bls12_377 with a Poseidon challenge, cheap to verify
+// in circuits over BLS12-377 Fr (the base field of the curve). Used by gadgets::schnorr.
+//
+//  sign:   R = k G, e = H(R.x, R.y, pk.x, pk.y, msg), s = k - e sk
+//  verify: e == H(s G + e pk, pk, msg)
+// The challenge e is an Fr element: it multiplies points as an integer, and s is e reduced
+// modulo the group order.
+
+#[derive(Clone, Debug)]
+pub struct SchnorrSignature {
+    pub s: ScalarF,
+    pub e: Fr,
+}
+
+#[derive(Clone, Debug)]
+pub struct SchnorrKeyPair {
+    pub sk: ScalarF,
+    pub pk: EdwardsAffine,
+}
+
+impl SchnorrKeyPair {
+    pub fn generate<R: Rng>(rng: &mut R) -> Self {
+        let sk = ScalarF::rand(rng);
+        let pk = (EdwardsAffine::generator() * sk).into_affine();
+        SchnorrKeyPair { sk, pk }
+    }
+
+    pub fn sign<R: Rng>(&self, params: &PoseidonConfig<Fr>, msg: &[Fr], rng: &mut R) -> SchnorrSignature {
+        let k = ScalarF::rand(rng);
+        let r = (EdwardsAffine::generator() * k).into_affine();
+        let e = schnorr_challenge(params, &r, &self.pk, msg);
+        let s = k - challenge_to_scalar(&e) * self.sk;
+        SchnorrSignature { s, e }
+    }
+}
+
+pub fn schnorr_parameters() -> PoseidonConfig<Fr> {
+    poseidon_parameters::<Fr>(2)
+}
+
+pub fn schnorr_challenge(params: &PoseidonConfig<Fr>, r: &EdwardsAffine, pk: &EdwardsAffine, msg: &[Fr]) -> Fr {
+    let mut input = vec![r.x, r.y, pk.x, pk.y];
+    input.extend_from_slice(msg);
+    poseidon::CRH::<Fr>::evaluate(params, input).unwrap()
+}
+
+pub fn challenge_to_scalar(e: &Fr) -> ScalarF {
+    ScalarF::from_le_bytes_mod_order(&e.into_bigint().to_bytes_le())
+}
+
+pub fn schnorr_verify(params: &PoseidonConfig<Fr>, pk: &EdwardsAffine, msg: &[Fr], signature: &SchnorrSignature) -> bool {
+    let r: EdwardsProjective = EdwardsAffine::generator() * signature.s + pk.mul_bigint(signature.e.into_bigint());
+    schnorr_challenge(params, &r.into_affine(), pk, msg) == signature.e
+}
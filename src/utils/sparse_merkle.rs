@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+
+use ark_bls12_377::Fr;
+use ark_crypto_primitives::{
+    crh::{poseidon, CRHScheme},
+    sponge::poseidon::PoseidonConfig,
+};
+use ark_ff::{ToConstraintField, Zero};
+use ark_std::{rand::Rng, UniformRand};
+
+use crate::utils::poseidon::poseidon_parameters;
+
+// Native sparse Merkle tree over BLS12-377 Fr with Poseidon, keyed by 256-bit keys.
+// Used to produce witnesses for gadgets::sparse_merkle and proofs::sparse_merkle.
+//
+// Every key has its own leaf: the leaf index is the key read as a little-endian integer,
+// so the child taken at height h is bit h of the key (bit i = key[i / 8] >> (i % 8)).
+// An absent key has the empty leaf 0; a present one H(key_0, key_1, value), where key_0, key_1
+// are the key packed 31 bytes per element. Inner nodes are H(left, right).
+
+pub const SMT_DEPTH: usize = 256;
+
+pub type SparseMerkleKey = [u8; 32];
+
+pub fn smt_parameters() -> PoseidonConfig<Fr> {
+    poseidon_parameters::<Fr>(2)
+}
+
+pub fn random_key<R: Rng>(rng: &mut R) -> SparseMerkleKey {
+    std::array::from_fn(|_| u8::rand(rng))
+}
+
+pub fn key_bits(key: &SparseMerkleKey) -> Vec<bool> {
+    (0..SMT_DEPTH).map(|i| (key[i / 8] >> (i % 8)) & 1 == 1).collect()
+}
+
+pub fn leaf_hash(params: &PoseidonConfig<Fr>, key: &SparseMerkleKey, value: Fr) -> Fr {
+    let mut input: Vec<Fr> = key.to_field_elements().unwrap();
+    input.push(value);
+    poseidon::CRH::<Fr>::evaluate(params, input).unwrap()
+}
+
+pub fn node_hash(params: &PoseidonConfig<Fr>, left: Fr, right: Fr) -> Fr {
+    poseidon::CRH::<Fr>::evaluate(params, [left, right]).unwrap()
+}
+
+// Siblings from the leaf up to the children of the root
+#[derive(Clone, Debug)]
+pub struct SparseMerklePath {
+    pub siblings: Vec<Fr>,
+}
+
+impl SparseMerklePath {
+    pub fn root(&self, params: &PoseidonConfig<Fr>, key: &SparseMerkleKey, leaf: Fr) -> Fr {
+        key_bits(key)
+            .iter()
+            .zip(&self.siblings)
+            .fold(leaf, |node, (bit, sibling)| {
+                if *bit {
+                    node_hash(params, *sibling, node)
+                } else {
+                    node_hash(params, node, *sibling)
+                }
+            })
+    }
+
+    pub fn verify_membership(&self, params: &PoseidonConfig<Fr>, root: &Fr, key: &SparseMerkleKey, value: Fr) -> bool {
+        self.root(params, key, leaf_hash(params, key, value)) == *root
+    }
+
+    pub fn verify_non_membership(&self, params: &PoseidonConfig<Fr>, root: &Fr, key: &SparseMerkleKey) -> bool {
+        self.root(params, key, Fr::zero()) == *root
+    }
+}
+
+#[derive(Clone)]
+pub struct SparseMerkleTree {
+    pub params: PoseidonConfig<Fr>,
+    // Root of an empty subtree of each height, 0 for the leaves
+    empty: Vec<Fr>,
+    // Non-empty nodes, by the key bits above them (bits h.. for a node of height h)
+    nodes: HashMap<Vec<bool>, Fr>,
+    values: HashMap<SparseMerkleKey, Fr>,
+}
+
+impl SparseMerkleTree {
+    pub fn new(params: PoseidonConfig<Fr>) -> Self {
+        let mut empty = vec![Fr::zero()];
+        for h in 0..SMT_DEPTH {
+            empty.push(node_hash(&params, empty[h], empty[h]));
+        }
+        SparseMerkleTree { params, empty, nodes: HashMap::new(), values: HashMap::new() }
+    }
+
+    pub fn root(&self) -> Fr {
+        self.node(SMT_DEPTH, &[])
+    }
+
+    pub fn get(&self, key: &SparseMerkleKey) -> Option<Fr> {
+        self.values.get(key).copied()
+    }
+
+    pub fn insert(&mut self, key: SparseMerkleKey, value: Fr) {
+        self.values.insert(key, value);
+        let leaf = leaf_hash(&self.params, &key, value);
+        self.update_path(&key, leaf);
+    }
+
+    pub fn remove(&mut self, key: &SparseMerkleKey) -> Option<Fr> {
+        let value = self.values.remove(key)?;
+        self.update_path(key, Fr::zero());
+        Some(value)
+    }
+
+    pub fn prove(&self, key: &SparseMerkleKey) -> SparseMerklePath {
+        let bits = key_bits(key);
+        let siblings = (0..SMT_DEPTH)
+            .map(|h| {
+                let mut sibling = bits[h..].to_vec();
+                sibling[0] = !sibling[0];
+                self.node(h, &sibling)
+            })
+            .collect();
+        SparseMerklePath { siblings }
+    }
+
+    fn node(&self, height: usize, bits: &[bool]) -> Fr {
+        self.nodes.get(bits).copied().unwrap_or(self.empty[height])
+    }
+
+    // Sets the leaf of `key` and recomputes the nodes above it; empty nodes are not stored
+    fn update_path(&mut self, key: &SparseMerkleKey, leaf: Fr) {
+        let bits = key_bits(key);
+        let mut node = leaf;
+        for h in 0..=SMT_DEPTH {
+            if node == self.empty[h] {
+                self.nodes.remove(&bits[h..]);
+            } else {
+                self.nodes.insert(bits[h..].to_vec(), node);
+            }
+            if h == SMT_DEPTH {
+                break;
+            }
+            let mut sibling_bits = bits[h..].to_vec();
+            sibling_bits[0] = !sibling_bits[0];
+            let sibling = self.node(h, &sibling_bits);
+            node = if bits[h] {
+                node_hash(&self.params, sibling, node)
+            } else {
+                node_hash(&self.params, node, sibling)
+            };
+        }
+    }
+}